@@ -1,7 +1,9 @@
 use anyhow::Result;
 use std::{
+    cmp::Reverse,
     collections::{HashMap, HashSet},
     fs,
+    ops::RangeInclusive,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,6 +60,7 @@ fn get_neighbor_positions(
     y: usize,
     map_width: usize,
     map_height: usize,
+    diagonal: bool,
 ) -> Vec<(usize, usize)> {
     let mut result = vec![];
 
@@ -77,20 +80,111 @@ fn get_neighbor_positions(
         result.push((x, y + 1));
     }
 
+    if diagonal {
+        for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+            let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+                continue;
+            };
+
+            if nx < map_width && ny < map_height {
+                result.push((nx, ny));
+            }
+        }
+    }
+
     result
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct TrailRules {
+    // Allowed height differences between two consecutive positions of a trail
+    deltas: RangeInclusive<isize>,
+    diagonal: bool,
+    start: usize,
+    end: usize,
+}
+
+impl Default for TrailRules {
+    fn default() -> Self {
+        Self {
+            deltas: 1..=1,
+            diagonal: false,
+            start: 0,
+            end: 9,
+        }
+    }
+}
+
+impl TrailRules {
+    fn ascending(&self) -> bool {
+        *self.deltas.start() > 0
+    }
+}
+
+// Fixed size set of small integers
+#[derive(Debug, Clone, PartialEq)]
+struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, value: usize) {
+        self.words[value / 64] |= 1 << (value % 64);
+    }
+
+    fn union(&mut self, other: &Bitset) {
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+struct BestTrails {
+    trailhead: (usize, usize),
+    rating: usize,
+    trails: Vec<Vec<(usize, usize)>>,
+}
+
 #[derive(Debug)]
 struct Graph {
     map: Map,
+    rules: TrailRules,
 
     // Adjecency list linking from (x, y) to a set of (x, y) positions,
-    // that are exactly 1 height above the current position
+    // that can follow the current position on a trail
     edges: HashMap<(usize, usize), HashSet<(usize, usize)>>,
 }
 
 impl Graph {
     fn new(map: Map) -> Self {
+        Self::with_rules(map, TrailRules::default())
+    }
+
+    // Trails have to climb or descend on every step, so they can never run in circles
+    fn with_rules(map: Map, rules: TrailRules) -> Self {
+        let (lowest, highest) = (*rules.deltas.start(), *rules.deltas.end());
+
+        if lowest > highest || lowest.signum() != highest.signum() || lowest == 0 {
+            panic!(
+                "Trails must strictly climb or descend, got {:?}",
+                rules.deltas
+            );
+        }
+
         let mut edges = Vec::new();
 
         for (y, row) in map.map.iter().enumerate() {
@@ -99,13 +193,17 @@ impl Graph {
                     continue;
                 };
 
-                let neighbors = get_neighbor_positions(x, y, map.width(), map.height());
+                let neighbors =
+                    get_neighbor_positions(x, y, map.width(), map.height(), rules.diagonal);
 
                 for (nx, ny) in neighbors {
                     match map.get(nx, ny) {
                         None | Some(Tile::Empty) => continue,
                         Some(Tile::Height(neighbor_height)) => {
-                            if neighbor_height == height + 1 {
+                            if rules
+                                .deltas
+                                .contains(&(neighbor_height as isize - *height as isize))
+                            {
                                 edges.push(((x, y), (nx, ny)));
                             }
                         }
@@ -123,18 +221,189 @@ impl Graph {
 
         Self {
             map,
+            rules,
             edges: adjecancy_list,
         }
     }
 
+    fn index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.map.width() + x
+    }
+
+    fn positions_with_height(&self, height: usize) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                if self.map.get(x, y) == Some(Tile::Height(height)) {
+                    result.push((x, y));
+                }
+            }
+        }
+
+        result
+    }
+
+    // All positions with a height, ordered against the direction of the trails. Every edge
+    // leads further up (or down), so all successors of a position come before the position
+    // itself.
+    fn layers(&self) -> Vec<(usize, usize)> {
+        let heights: Vec<usize> = if self.rules.ascending() {
+            (0..=9).rev().collect()
+        } else {
+            (0..=9).collect()
+        };
+
+        heights
+            .into_iter()
+            .flat_map(|height| self.positions_with_height(height))
+            .collect()
+    }
+
+    // Number of distinct trails from every position to any summit, which is the sum over
+    // its successors, so it is computed once per position, layer by layer
+    fn paths_to_summit(&self) -> Vec<usize> {
+        let mut paths = vec![0; self.map.width() * self.map.height()];
+
+        for position in self.layers() {
+            paths[self.index(position)] =
+                if self.map.get(position.0, position.1) == Some(Tile::Height(self.rules.end)) {
+                    1
+                } else {
+                    self.edges.get(&position).map_or(0, |next| {
+                        next.iter().map(|next| paths[self.index(*next)]).sum()
+                    })
+                };
+        }
+
+        paths
+    }
+
+    // Sum of the number of distinct trails starting at every trailhead
+    fn rating(&self) -> usize {
+        let paths = self.paths_to_summit();
+
+        self.positions_with_height(self.rules.start)
+            .iter()
+            .map(|trailhead| paths[self.index(*trailhead)])
+            .sum()
+    }
+
+    // Finds the trailhead with the most trails and lists up to `limit` of its trails
+    fn best_trails(&self, limit: usize) -> Option<BestTrails> {
+        let paths = self.paths_to_summit();
+
+        let trailhead = self
+            .positions_with_height(self.rules.start)
+            .into_iter()
+            .rev()
+            .max_by_key(|trailhead| paths[self.index(*trailhead)])?;
+
+        let rating = paths[self.index(trailhead)];
+
+        let mut result = vec![];
+        let mut stack = vec![vec![trailhead]];
+
+        // Only positions from which a summit can be reached are followed, so every path on
+        // the stack ends up as a trail
+        while let Some(path) = stack.pop() {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (x, y) = *path.last().unwrap();
+
+            if self.map.get(x, y) == Some(Tile::Height(self.rules.end)) {
+                result.push(path);
+                continue;
+            }
+
+            let mut next = self
+                .edges
+                .get(&(x, y))
+                .into_iter()
+                .flatten()
+                .filter(|next| paths[self.index(**next)] > 0)
+                .collect::<Vec<_>>();
+
+            next.sort_by_key(|(x, y)| Reverse((*y, *x)));
+
+            for position in next {
+                let mut new_path = path.clone();
+                new_path.push(*position);
+                stack.push(new_path);
+            }
+        }
+
+        Some(BestTrails {
+            trailhead,
+            rating,
+            trails: result,
+        })
+    }
+
+    fn render_trail(&self, trail: &[(usize, usize)]) -> String {
+        let mut result = String::new();
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                match self.map.get(x, y) {
+                    Some(Tile::Height(height)) if trail.contains(&(x, y)) => {
+                        result.push(char::from_digit(height as u32, 10).unwrap())
+                    }
+                    _ => result.push('.'),
+                }
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+
+    // Sum of the number of summits reachable from every trailhead. Every position keeps the
+    // set of summits it can reach as a bitset, which is the union over its successors.
+    fn score(&self) -> usize {
+        let summits = self.positions_with_height(self.rules.end);
+
+        let mut summit_ids = vec![None; self.map.width() * self.map.height()];
+
+        for (id, summit) in summits.iter().enumerate() {
+            summit_ids[self.index(*summit)] = Some(id);
+        }
+
+        let mut reachable = vec![Bitset::new(0); self.map.width() * self.map.height()];
+
+        for position in self.layers() {
+            let mut set = Bitset::new(summits.len());
+
+            if let Some(id) = summit_ids[self.index(position)] {
+                set.insert(id);
+            }
+
+            for next in self.edges.get(&position).into_iter().flatten() {
+                set.union(&reachable[self.index(*next)]);
+            }
+
+            reachable[self.index(position)] = set;
+        }
+
+        self.positions_with_height(self.rules.start)
+            .iter()
+            .map(|trailhead| reachable[self.index(*trailhead)].len())
+            .sum()
+    }
+
+    // Enumerates every single trail, which takes exponential time. Only used to check the
+    // faster computations above.
+    #[cfg(test)]
     fn find_unique_paths(&self) -> usize {
         let mut paths = HashSet::new();
 
         for y in 0..self.map.height() {
             for x in 0..self.map.width() {
-                match self.map.get(x, y) {
-                    Some(Tile::Height(0)) => {}
-                    _ => continue,
+                if self.map.get(x, y) != Some(Tile::Height(self.rules.start)) {
+                    continue;
                 }
 
                 let mut queue = vec![vec![(x, y)]];
@@ -143,8 +412,9 @@ impl Graph {
                     let (x, y) = path.last().unwrap().clone();
 
                     match self.map.get(x, y) {
-                        Some(Tile::Height(h)) if h == 9 => {
+                        Some(Tile::Height(h)) if h == self.rules.end => {
                             paths.insert(path.clone());
+                            continue;
                         }
                         _ => {}
                     }
@@ -165,6 +435,7 @@ impl Graph {
         paths.len()
     }
 
+    #[cfg(test)]
     fn get_reachable_positions_with_height(
         &self,
         x: usize,
@@ -202,18 +473,20 @@ impl Graph {
         result
     }
 
-    // Returns a hashmap from a trailhead position with height 0 to a set of reachable positions with height 9
+    // Returns a hashmap from a trailhead position to a set of reachable summit positions
+    #[cfg(test)]
     fn find_all_trailheads(&self) -> HashMap<(usize, usize), HashSet<(usize, usize)>> {
         let mut result: HashMap<(usize, usize), HashSet<(usize, usize)>> = HashMap::new();
 
         for y in 0..self.map.height() {
             for x in 0..self.map.width() {
-                match self.map.get(x, y) {
-                    Some(Tile::Height(0)) => {}
-                    _ => continue,
+                if self.map.get(x, y) != Some(Tile::Height(self.rules.start)) {
+                    continue;
                 }
 
-                result.insert((x, y), self.get_reachable_positions_with_height(x, y, 9));
+                let summits = self.get_reachable_positions_with_height(x, y, self.rules.end);
+
+                result.insert((x, y), summits);
             }
         }
 
@@ -227,14 +500,24 @@ fn main() -> Result<()> {
     let map = Map::parse(&input);
     let graph = Graph::new(map);
 
-    let trailheads = graph.find_all_trailheads();
-    let result: usize = trailheads.values().map(|x| x.len()).sum();
+    let result = graph.score();
 
     println!("Result (Part 1): {result}");
 
-    let unique_path_count = graph.find_unique_paths();
+    let result = graph.rating();
+
+    println!("Result (Part 2): {result}");
 
-    println!("Result (Part 2): {unique_path_count}");
+    if let Some(best) = graph.best_trails(1) {
+        println!(
+            "Best trailhead at {:?} with {} trails, for example:",
+            best.trailhead, best.rating
+        );
+
+        for trail in best.trails {
+            println!("{}", graph.render_trail(&trail));
+        }
+    }
 
     Ok(())
 }
@@ -442,4 +725,260 @@ mod tests {
 
         assert_eq!(unique_paths, 81);
     }
+
+    const EXAMPLES: [&str; 6] = [
+        r#"
+...0...
+...1...
+...2...
+6543456
+7.....7
+8.....8
+9.....9
+        "#,
+        r#"
+10..9..
+2...8..
+3...7..
+4567654
+...8..3
+...9..2
+.....01
+        "#,
+        r#"
+.....0.
+..4321.
+..5..2.
+..6543.
+..7..4.
+..8765.
+..9....
+        "#,
+        r#"
+..90..9
+...1.98
+...2..7
+6543456
+765.987
+876....
+987....
+        "#,
+        r#"
+012345
+123456
+234567
+345678
+4.6789
+56789.
+        "#,
+        r#"
+89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732
+        "#,
+    ];
+
+    #[test]
+    fn test_rating_matches_enumeration() {
+        for input in EXAMPLES {
+            let graph = Graph::new(Map::parse(input));
+
+            assert_eq!(graph.rating(), graph.find_unique_paths(), "{input}");
+        }
+
+        let graph = Graph::new(Map::parse(EXAMPLES[5]));
+        assert_eq!(graph.rating(), 81);
+    }
+
+    #[test]
+    fn test_score_matches_search() {
+        for input in EXAMPLES {
+            let graph = Graph::new(Map::parse(input));
+
+            let expected: usize = graph.find_all_trailheads().values().map(|x| x.len()).sum();
+
+            assert_eq!(graph.score(), expected, "{input}");
+        }
+
+        let graph = Graph::new(Map::parse(EXAMPLES[5]));
+        assert_eq!(graph.score(), 36);
+    }
+
+    #[test]
+    fn test_rating_many_trails() {
+        // Every monotone path through the grid is a trail, so the rating grows exponentially
+        let input = (0..10)
+            .map(|y| {
+                (0..10)
+                    .map(|x| char::from(b'0' + ((x + y) % 10) as u8))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let graph = Graph::new(Map::parse(&input));
+
+        assert_eq!(graph.rating(), graph.find_unique_paths());
+    }
+
+    #[test]
+    fn test_bitset() {
+        let mut a = Bitset::new(130);
+        a.insert(0);
+        a.insert(129);
+
+        let mut b = Bitset::new(130);
+        b.insert(64);
+        b.insert(129);
+
+        a.union(&b);
+
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.words, vec![1, 1, 2]);
+    }
+
+    fn check_against_oracle(rules: TrailRules) {
+        for input in EXAMPLES {
+            let graph = Graph::with_rules(Map::parse(input), rules.clone());
+
+            let expected: usize = graph.find_all_trailheads().values().map(|x| x.len()).sum();
+
+            assert_eq!(graph.rating(), graph.find_unique_paths(), "{input}");
+            assert_eq!(graph.score(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_diagonal_rules() {
+        let input = r#"
+0.
+.1
+        "#;
+
+        let rules = TrailRules {
+            diagonal: true,
+            end: 1,
+            ..TrailRules::default()
+        };
+
+        let graph = Graph::with_rules(Map::parse(input), rules.clone());
+        assert_eq!(graph.rating(), 1);
+
+        let graph = Graph::with_rules(
+            Map::parse(input),
+            TrailRules {
+                diagonal: false,
+                ..rules.clone()
+            },
+        );
+        assert_eq!(graph.rating(), 0);
+
+        check_against_oracle(TrailRules {
+            diagonal: true,
+            ..TrailRules::default()
+        });
+    }
+
+    #[test]
+    fn test_delta_range_rules() {
+        check_against_oracle(TrailRules {
+            deltas: 1..=3,
+            ..TrailRules::default()
+        });
+
+        check_against_oracle(TrailRules {
+            deltas: 2..=2,
+            diagonal: true,
+            ..TrailRules::default()
+        });
+    }
+
+    #[test]
+    fn test_descending_rules() {
+        let rules = TrailRules {
+            deltas: -1..=-1,
+            start: 9,
+            end: 0,
+            ..TrailRules::default()
+        };
+
+        // Walking down every trail gives the same trails in reverse
+        for input in EXAMPLES {
+            let up = Graph::new(Map::parse(input));
+            let down = Graph::with_rules(Map::parse(input), rules.clone());
+
+            assert_eq!(up.rating(), down.rating(), "{input}");
+        }
+
+        check_against_oracle(rules);
+    }
+
+    #[test]
+    fn test_start_and_end_rules() {
+        check_against_oracle(TrailRules {
+            start: 2,
+            end: 5,
+            ..TrailRules::default()
+        });
+
+        check_against_oracle(TrailRules {
+            deltas: -2..=-1,
+            start: 8,
+            end: 3,
+            diagonal: true,
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_flat_trails_rejected() {
+        let rules = TrailRules {
+            deltas: 0..=1,
+            ..TrailRules::default()
+        };
+
+        Graph::with_rules(Map::parse(EXAMPLES[0]), rules);
+    }
+
+    #[test]
+    fn test_best_trails() {
+        let graph = Graph::new(Map::parse(EXAMPLES[5]));
+
+        let best = graph.best_trails(usize::MAX).unwrap();
+
+        assert_eq!(best.trailhead, (4, 0));
+        assert_eq!(best.rating, 24);
+        assert_eq!(best.trails.len(), 24);
+        assert_eq!(best.trails.iter().collect::<HashSet<_>>().len(), 24);
+
+        for trail in best.trails.iter() {
+            assert_eq!(trail.len(), 10);
+            assert_eq!(trail[0], (4, 0));
+
+            for (height, (x, y)) in trail.iter().enumerate() {
+                assert_eq!(graph.map.get(*x, *y), Some(Tile::Height(height)));
+            }
+        }
+
+        assert_eq!(graph.best_trails(3).unwrap().trails.len(), 3);
+    }
+
+    #[test]
+    fn test_render_trail() {
+        let graph = Graph::new(Map::parse(EXAMPLES[0]));
+
+        let best = graph.best_trails(1).unwrap();
+
+        assert_eq!(best.rating, 2);
+        assert_eq!(
+            graph.render_trail(&best.trails[0]),
+            ["...0...", "...1...", "...2...", "6543...", "7......", "8......", "9......", "",]
+                .join("\n")
+        );
+    }
 }
@@ -1,36 +1,223 @@
-use std::fs;
+use std::{collections::HashMap, fs, ops::AddAssign};
 
-use memoize::memoize;
+use num_bigint::BigUint;
 
-#[memoize]
-fn parts_after_steps(stone: usize, steps: usize) -> usize {
-    if steps == 0 {
-        return 1;
+fn count_digits(mut stone: u64, base: u64) -> u32 {
+    let mut result = 1;
+
+    while stone >= base {
+        stone /= base;
+        result += 1;
+    }
+
+    result
+}
+
+// Splits a stone into `arity` parts with the same number of digits each, if its digits can
+// be divided evenly
+fn split_digits(stone: u64, base: u64, arity: u32) -> Option<Vec<u64>> {
+    let digits = count_digits(stone, base);
+
+    if !digits.is_multiple_of(arity) {
+        return None;
+    }
+
+    let divisor = base.pow(digits / arity);
+
+    let mut result = vec![0; arity as usize];
+    let mut rest = stone;
+
+    for part in result.iter_mut().rev() {
+        *part = rest % divisor;
+        rest /= divisor;
+    }
+
+    Some(result)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    Equals(u64),
+    DigitsDivisible { base: u64, arity: u32 },
+    Always,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Transform {
+    Replace(u64),
+    Split { base: u64, arity: u32 },
+    Multiply(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleError {
+    InvalidBase(u64),
+    InvalidArity(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    predicate: Predicate,
+    transform: Transform,
+}
+
+impl Rule {
+    fn replace(stone: u64, replacement: u64) -> Self {
+        Rule {
+            predicate: Predicate::Equals(stone),
+            transform: Transform::Replace(replacement),
+        }
+    }
+
+    // Digits only exist in bases of at least two, and a split needs at least two parts
+    fn split(base: u64, arity: u32) -> Result<Self, RuleError> {
+        if base < 2 {
+            return Err(RuleError::InvalidBase(base));
+        }
+
+        if arity < 2 {
+            return Err(RuleError::InvalidArity(arity));
+        }
+
+        Ok(Rule {
+            predicate: Predicate::DigitsDivisible { base, arity },
+            transform: Transform::Split { base, arity },
+        })
+    }
+
+    fn multiply(factor: u64) -> Self {
+        Rule {
+            predicate: Predicate::Always,
+            transform: Transform::Multiply(factor),
+        }
     }
 
-    if stone == 0 {
-        return parts_after_steps(1, steps - 1);
+    fn matches(&self, stone: u64) -> bool {
+        match self.predicate {
+            Predicate::Equals(value) => stone == value,
+            Predicate::DigitsDivisible { base, arity } => {
+                count_digits(stone, base).is_multiple_of(arity)
+            }
+            Predicate::Always => true,
+        }
     }
 
-    let string = format!("{stone}");
-    if string.len() % 2 == 0 {
-        let middle = string.len() / 2;
-        let iter = string.chars();
-        let a = iter.clone().take(middle).collect::<String>();
-        let b = iter.skip(middle).collect::<String>();
+    fn transform(&self, stone: u64) -> Vec<u64> {
+        match self.transform {
+            Transform::Replace(replacement) => vec![replacement],
+            Transform::Split { base, arity } => {
+                let Some(parts) = split_digits(stone, base, arity) else {
+                    panic!("Stone {stone} can not be split into {arity} parts");
+                };
+
+                parts
+            }
+            Transform::Multiply(factor) => {
+                let Some(stone) = stone.checked_mul(factor) else {
+                    panic!("Stone {stone} grew too large");
+                };
+
+                vec![stone]
+            }
+        }
+    }
+}
+
+// Rules are checked in order and the first one matching a stone transforms it. Stones
+// which match no rule stay as they are.
+#[derive(Debug, Clone, PartialEq)]
+struct Rules {
+    rules: Vec<Rule>,
+}
 
-        return parts_after_steps(a.parse().unwrap(), steps - 1)
-            + parts_after_steps(b.parse().unwrap(), steps - 1);
+impl Rules {
+    fn standard() -> Self {
+        Rules {
+            rules: vec![
+                Rule::replace(0, 1),
+                Rule::split(10, 2).unwrap(),
+                Rule::multiply(2024),
+            ],
+        }
     }
 
-    parts_after_steps(stone * 2024, steps - 1)
+    fn apply(&self, stone: u64) -> Vec<u64> {
+        match self.rules.iter().find(|rule| rule.matches(stone)) {
+            Some(rule) => rule.transform(stone),
+            None => vec![stone],
+        }
+    }
 }
 
-fn arr_parts_after_steps(stones: &[usize], steps: usize) -> usize {
-    stones
-        .iter()
-        .map(|stone| parts_after_steps(*stone, steps))
-        .sum()
+// The order of the stones never matters, so only the number of stones with every value is
+// tracked. There are only a few thousand distinct values, no matter how often the stones
+// blink, so `C` only needs to be big enough for the total number of stones. `u128` lasts
+// for about 200 blinks, beyond that `BigUint` works.
+#[derive(Debug, Clone, PartialEq)]
+struct Stones<C> {
+    counts: HashMap<u64, C>,
+}
+
+impl<C> Stones<C>
+where
+    C: Clone + Default + From<u8> + for<'a> AddAssign<&'a C>,
+{
+    fn new(stones: &[u64]) -> Self {
+        let mut counts: HashMap<u64, C> = HashMap::new();
+
+        for stone in stones {
+            *counts.entry(*stone).or_default() += &C::from(1);
+        }
+
+        Self { counts }
+    }
+
+    fn blink(&self, rules: &Rules) -> Self {
+        let mut counts: HashMap<u64, C> = HashMap::with_capacity(self.counts.len());
+
+        for (stone, count) in self.counts.iter() {
+            for stone in rules.apply(*stone) {
+                *counts.entry(stone).or_default() += count;
+            }
+        }
+
+        Self { counts }
+    }
+
+    fn len(&self) -> C {
+        let mut result = C::default();
+
+        for count in self.counts.values() {
+            result += count;
+        }
+
+        result
+    }
+
+    fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+// Returns the number of stones after the given number of blinks and the number of distinct
+// stone values after every blink, starting with the initial arrangement
+fn evolve<C>(stones: &[u64], rules: &Rules, blinks: usize) -> (C, Vec<usize>)
+where
+    C: Clone + Default + From<u8> + for<'a> AddAssign<&'a C>,
+{
+    let mut stones = Stones::<C>::new(stones);
+    let mut distinct = vec![stones.distinct()];
+
+    for _ in 0..blinks {
+        stones = stones.blink(rules);
+        distinct.push(stones.distinct());
+    }
+
+    (stones.len(), distinct)
+}
+
+fn arr_parts_after_steps(stones: &[u64], steps: usize) -> u128 {
+    evolve::<u128>(stones, &Rules::standard(), steps).0
 }
 
 fn main() {
@@ -39,20 +226,37 @@ fn main() {
     let stones = input
         .trim()
         .split(' ')
-        .map(|x| x.parse::<usize>().unwrap())
-        .collect::<Vec<usize>>();
+        .map(|x| x.parse::<u64>().unwrap())
+        .collect::<Vec<u64>>();
 
-    let result: usize = arr_parts_after_steps(&stones, 25);
+    let result = arr_parts_after_steps(&stones, 25);
 
     println!("Result (Part 1): {result}");
 
-    let result: usize = arr_parts_after_steps(&stones, 75);
+    let rules = Rules::standard();
+
+    let (result, distinct) = evolve::<u128>(&stones, &rules, 75);
 
     println!("Result (Part 2): {result}");
+
+    println!(
+        "Distinct stone values after 75 blinks: {}",
+        distinct.last().unwrap()
+    );
+
+    let (count, distinct) = evolve::<BigUint>(&stones, &rules, 1000);
+
+    println!(
+        "After 1000 blinks: {} stones with {} distinct values",
+        count,
+        distinct.last().unwrap()
+    );
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
     #[test]
@@ -90,4 +294,156 @@ mod tests {
             22, // 2097446912, 14168, 4048, 2, 0, 2, 4, 40, 48, 2024, 40, 48, 80, 96, 2, 8, 6, 7, 6, 0, 3, 2
         );
     }
+
+    #[test]
+    fn test_split_digits() {
+        assert_eq!(split_digits(0, 10, 2), None);
+        assert_eq!(split_digits(7, 10, 2), None);
+        assert_eq!(split_digits(10, 10, 2), Some(vec![1, 0]));
+        assert_eq!(split_digits(999, 10, 2), None);
+        assert_eq!(split_digits(1000, 10, 2), Some(vec![10, 0]));
+        assert_eq!(split_digits(253000, 10, 2), Some(vec![253, 0]));
+        assert_eq!(split_digits(28676032, 10, 2), Some(vec![2867, 6032]));
+        assert_eq!(
+            split_digits(10_000_000_000_000_000_000, 10, 2),
+            Some(vec![1_000_000_000, 0])
+        );
+
+        assert_eq!(split_digits(123456, 10, 3), Some(vec![12, 34, 56]));
+        assert_eq!(split_digits(1234, 10, 3), None);
+        assert_eq!(split_digits(0b1101, 2, 2), Some(vec![0b11, 0b01]));
+        assert_eq!(split_digits(0xabc, 16, 3), Some(vec![0xa, 0xb, 0xc]));
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::standard();
+
+        assert_eq!(rules.apply(0), vec![1]);
+        assert_eq!(rules.apply(1), vec![2024]);
+        assert_eq!(rules.apply(1000), vec![10, 0]);
+        assert_eq!(rules.apply(999), vec![2021976]);
+
+        // Order matters, 10 would otherwise be split
+        let rules = Rules {
+            rules: vec![Rule::replace(10, 7), Rule::split(10, 2).unwrap()],
+        };
+
+        assert_eq!(rules.apply(10), vec![7]);
+        assert_eq!(rules.apply(11), vec![1, 1]);
+        assert_eq!(rules.apply(111), vec![111]);
+    }
+
+    // Deterministic pseudo random numbers, good enough to vary the inputs
+    fn random(seed: &mut u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *seed >> 33
+    }
+
+    fn simulate(stones: &[u64], rules: &Rules, blinks: usize) -> Vec<Vec<u64>> {
+        let mut result = vec![stones.to_vec()];
+
+        for _ in 0..blinks {
+            let next = result
+                .last()
+                .unwrap()
+                .iter()
+                .flat_map(|stone| rules.apply(*stone))
+                .collect();
+
+            result.push(next);
+        }
+
+        result
+    }
+
+    #[test]
+    fn test_rules_against_simulation() {
+        let variants = [
+            Rules::standard(),
+            Rules {
+                rules: vec![
+                    Rule::replace(0, 1),
+                    Rule::split(10, 3).unwrap(),
+                    Rule::multiply(7),
+                ],
+            },
+            Rules {
+                rules: vec![Rule::split(2, 2).unwrap(), Rule::multiply(3)],
+            },
+            Rules {
+                rules: vec![
+                    Rule::replace(0, 5),
+                    Rule::replace(1, 0),
+                    Rule::split(16, 2).unwrap(),
+                    Rule::multiply(11),
+                ],
+            },
+        ];
+
+        let mut seed = 42;
+
+        for rules in variants.iter() {
+            for _ in 0..10 {
+                let stones = (0..random(&mut seed) % 5 + 1)
+                    .map(|_| random(&mut seed) % 1000)
+                    .collect::<Vec<_>>();
+
+                let blinks = 12;
+                let steps = simulate(&stones, rules, blinks);
+
+                let (count, distinct) = evolve::<u128>(&stones, rules, blinks);
+
+                assert_eq!(count, steps[blinks].len() as u128, "{stones:?} {rules:?}");
+
+                let expected = steps
+                    .iter()
+                    .map(|stones| stones.iter().collect::<HashSet<_>>().len())
+                    .collect::<Vec<_>>();
+
+                assert_eq!(distinct, expected, "{stones:?} {rules:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(arr_parts_after_steps(&[125, 17], 6), 22);
+        assert_eq!(arr_parts_after_steps(&[125, 17], 25), 55312);
+        assert_eq!(arr_parts_after_steps(&[125, 17], 75), 65601038650482);
+    }
+
+    #[test]
+    fn test_distinct() {
+        let (count, distinct) = evolve::<u128>(&[125, 17], &Rules::standard(), 6);
+
+        assert_eq!(count, 22);
+        // 2097446912, 14168, 4048, 2, 0, 2, 4, 40, 48, 2024, 40, 48, 80, 96, 2, 8, 6, 7, 6, 0, 3, 2
+        assert_eq!(distinct, vec![2, 3, 4, 5, 8, 12, 15]);
+    }
+
+    #[test]
+    fn test_many_blinks() {
+        let (small, _) = evolve::<u128>(&[125, 17], &Rules::standard(), 150);
+        let (big, _) = evolve::<BigUint>(&[125, 17], &Rules::standard(), 150);
+
+        assert_eq!(BigUint::from(small), big);
+
+        let (count, distinct) = evolve::<BigUint>(&[125, 17], &Rules::standard(), 2000);
+
+        assert!(count > BigUint::from(u128::MAX));
+        assert_eq!(distinct.len(), 2001);
+        assert!(distinct.iter().all(|distinct| *distinct < 5000));
+    }
+
+    #[test]
+    fn test_split_rule_validation() {
+        assert_eq!(Rule::split(0, 2), Err(RuleError::InvalidBase(0)));
+        assert_eq!(Rule::split(1, 2), Err(RuleError::InvalidBase(1)));
+        assert_eq!(Rule::split(10, 0), Err(RuleError::InvalidArity(0)));
+        assert_eq!(Rule::split(10, 1), Err(RuleError::InvalidArity(1)));
+        assert!(Rule::split(2, 2).is_ok());
+    }
 }
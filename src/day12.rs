@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fs};
+use std::{collections::BTreeSet, fs};
 
 #[derive(Clone)]
 struct Map {
@@ -29,113 +29,223 @@ impl Map {
     }
 }
 
-fn find_unvisited(
-    visited: &HashSet<(usize, usize)>,
-    width: usize,
-    height: usize,
-) -> Option<(usize, usize)> {
-    for y in 0..height {
-        for x in 0..width {
-            if !visited.contains(&(x, y)) {
-                return Some((x, y));
-            }
-        }
-    }
-
-    None
-}
-
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Area {
+    plant: char,
     area: usize,
     perimiter: usize,
-    tiles: HashSet<(usize, usize)>,
+    sides: usize,
+    // Inclusive corners of the bounding box
+    min: (usize, usize),
+    max: (usize, usize),
+    holes: usize,
+    // Corners of the outer fence in clockwise order
+    outline: Vec<(usize, usize)>,
 }
 
-fn get_areas(input: &Map) -> Vec<Area> {
-    let mut result = Vec::new();
+struct Regions {
+    width: usize,
+    height: usize,
+    // Index of the area every tile belongs to, row by row
+    labels: Vec<usize>,
+    areas: Vec<Area>,
+    // Pairs of neighbouring areas, smaller index first
+    adjacent: BTreeSet<(usize, usize)>,
+}
 
-    let mut visited: HashSet<(usize, usize)> = HashSet::new();
+impl Regions {
+    // Labels every tile with a single flood fill per area, so every tile is visited once
+    fn label(map: &Map) -> Self {
+        let width = map.width();
+        let height = map.height();
 
-    let mut x = 0;
-    let mut y = 0;
+        let mut labels = vec![usize::MAX; width * height];
+        let mut areas = vec![];
+        let mut starts = vec![];
 
-    let mut current_area = Area {
-        area: 0,
-        perimiter: 0,
-        tiles: HashSet::new(),
-    };
+        for start in 0..width * height {
+            if labels[start] != usize::MAX {
+                continue;
+            }
 
-    loop {
-        let mut stack = vec![(x, y)];
+            let id = areas.len();
+            let plant = map.get_tile(start % width, start / width);
+
+            let mut area = Area {
+                plant,
+                area: 0,
+                perimiter: 0,
+                sides: 0,
+                min: (start % width, start / width),
+                max: (start % width, start / width),
+                holes: 0,
+                outline: vec![],
+            };
+
+            labels[start] = id;
+            starts.push(start);
+
+            let mut stack = vec![start];
+
+            while let Some(index) = stack.pop() {
+                let (x, y) = (index % width, index / width);
+
+                area.area += 1;
+                area.min = (area.min.0.min(x), area.min.1.min(y));
+                area.max = (area.max.0.max(x), area.max.1.max(y));
+
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+
+                for (nx, ny) in neighbours {
+                    if nx >= width || ny >= height || map.get_tile(nx, ny) != plant {
+                        area.perimiter += 1;
+                        continue;
+                    }
+
+                    let neighbour = ny * width + nx;
+
+                    if labels[neighbour] == usize::MAX {
+                        labels[neighbour] = id;
+                        stack.push(neighbour);
+                    }
+                }
+            }
 
-        while let Some((x1, y1)) = stack.pop() {
-            x = x1;
-            y = y1;
+            areas.push(area);
+        }
 
-            if visited.contains(&(x, y)) {
-                continue;
-            }
+        let mut regions = Regions {
+            width,
+            height,
+            labels,
+            areas,
+            adjacent: BTreeSet::new(),
+        };
 
-            let c = input.get_tile(x, y);
+        regions.measure(&starts);
 
-            visited.insert((x, y));
-            current_area.tiles.insert((x, y));
-            current_area.area += 1;
+        regions
+    }
 
-            if x < 1 {
-                // Out of bounds
-                current_area.perimiter += 1;
-            } else if input.get_tile(x - 1, y) != c {
-                current_area.perimiter += 1;
-            } else if !visited.contains(&(x - 1, y)) {
-                stack.push((x - 1, y))
-            }
+    fn label_at(&self, x: isize, y: isize) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
 
-            if y < 1 {
-                // Out of bounds
-                current_area.perimiter += 1;
-            } else if input.get_tile(x, y - 1) != c {
-                current_area.perimiter += 1;
-            } else if !visited.contains(&(x, y - 1)) {
-                stack.push((x, y - 1))
-            }
+        Some(self.labels[y as usize * self.width + x as usize])
+    }
 
-            if x >= input.width() - 1 {
-                // Out of bounds
-                current_area.perimiter += 1;
-            } else if input.get_tile(x + 1, y) != c {
-                current_area.perimiter += 1;
-            } else if !visited.contains(&(x + 1, y)) {
-                stack.push((x + 1, y))
+    // Finds sides, holes and neighbours by looking at every 2x2 window around every tile.
+    // Windows with one or three tiles of an area are corners, and windows with two
+    // diagonal tiles are two corners at once. The same windows give the Euler number of
+    // the area, which is one minus the number of holes.
+    fn measure(&mut self, starts: &[usize]) {
+        let mut single = vec![0; self.areas.len()];
+        let mut triple = vec![0; self.areas.len()];
+        let mut diagonal = vec![0; self.areas.len()];
+
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let id = self.label_at(x, y).unwrap();
+
+                for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                    if let Some(other) = self.label_at(nx, ny) {
+                        if other != id {
+                            self.adjacent.insert((id.min(other), id.max(other)));
+                        }
+                    }
+                }
+
+                for (wx, wy) in [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)] {
+                    let window = [(wx, wy), (wx + 1, wy), (wx, wy + 1), (wx + 1, wy + 1)];
+                    let inside = window.map(|(x, y)| self.label_at(x, y) == Some(id));
+
+                    // Every window is only counted by the first tile of the area inside it
+                    let first = inside.iter().position(|inside| *inside).unwrap();
+
+                    if window[first] != (x, y) {
+                        continue;
+                    }
+
+                    match inside {
+                        [true, false, false, true] | [false, true, true, false] => {
+                            diagonal[id] += 1
+                        }
+                        _ => match inside.iter().filter(|inside| **inside).count() {
+                            1 => single[id] += 1,
+                            3 => triple[id] += 1,
+                            _ => {}
+                        },
+                    }
+                }
             }
+        }
 
-            if y >= input.height() - 1 {
-                // Out of bounds
-                current_area.perimiter += 1;
-            } else if input.get_tile(x, y + 1) != c {
-                current_area.perimiter += 1;
-            } else if !visited.contains(&(x, y + 1)) {
-                stack.push((x, y + 1))
-            }
+        for id in 0..self.areas.len() {
+            let euler = (single[id] as isize - triple[id] as isize - 2 * diagonal[id] as isize) / 4;
+
+            self.areas[id].sides = single[id] + triple[id] + 2 * diagonal[id];
+            self.areas[id].holes = (1 - euler) as usize;
+            self.areas[id].outline = self.trace_outline(id, starts[id]);
         }
+    }
 
-        result.push(current_area);
-        current_area = Area {
-            area: 0,
-            perimiter: 0,
-            tiles: HashSet::new(),
-        };
+    // Walks along the outer fence with the area on the right hand side, starting at the top
+    // left corner of its first tile and preferring left turns
+    fn trace_outline(&self, id: usize, first: usize) -> Vec<(usize, usize)> {
+        let inside = |x: isize, y: isize| self.label_at(x, y) == Some(id);
+
+        let start = ((first % self.width) as isize, (first / self.width) as isize);
+
+        // East, south, west, north
+        let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+        let mut result = vec![(start.0 as usize, start.1 as usize)];
+        let mut position = start;
+        let mut direction = 0;
+
+        loop {
+            let (dx, dy) = directions[direction];
+            position = (position.0 + dx, position.1 + dy);
+
+            // The tiles ahead of the corner, left and right of the walking direction
+            let (x, y) = position;
+            let (left, right) = match direction {
+                0 => (inside(x, y - 1), inside(x, y)),
+                1 => (inside(x, y), inside(x - 1, y)),
+                2 => (inside(x - 1, y), inside(x - 1, y - 1)),
+                _ => (inside(x - 1, y - 1), inside(x, y - 1)),
+            };
+
+            let next = if left {
+                (direction + 3) % 4
+            } else if right {
+                direction
+            } else {
+                (direction + 1) % 4
+            };
+
+            if position == start && next == 0 {
+                break;
+            }
 
-        if let Some((x1, y1)) = find_unvisited(&visited, input.width(), input.height()) {
-            x = x1;
-            y = y1;
-        } else {
-            break;
+            if next != direction {
+                result.push((x as usize, y as usize));
+                direction = next;
+            }
         }
+
+        result
     }
+}
 
-    result
+fn get_areas(input: &Map) -> Vec<Area> {
+    Regions::label(input).areas
 }
 
 fn main() {
@@ -148,6 +258,10 @@ fn main() {
     let result: usize = data.iter().map(|area| area.area * area.perimiter).sum();
 
     println!("Result (Part 1): {result}");
+
+    let result: usize = data.iter().map(|area| area.area * area.sides).sum();
+
+    println!("Result (Part 2): {result}");
 }
 
 #[cfg(test)]
@@ -274,4 +388,234 @@ MMMISSJEEE
         dbg!(&areas);
         assert_eq!(values, expected);
     }
+
+    fn bulk_price(input: &str) -> usize {
+        let map = Map::parse(input);
+
+        get_areas(&map)
+            .iter()
+            .map(|area| area.area * area.sides)
+            .sum()
+    }
+
+    #[test]
+    fn test_sides_first() {
+        let input = r#"
+AAAA
+BBCD
+BBCC
+EEEC
+        "#;
+
+        let map = Map::parse(input);
+
+        let mut values = get_areas(&map)
+            .iter()
+            .map(|area| (area.area, area.sides))
+            .collect::<Vec<_>>();
+
+        values.sort();
+
+        let mut expected = vec![
+            (4, 4), // A
+            (4, 4), // B
+            (4, 8), // C
+            (1, 4), // D
+            (3, 4), // E
+        ];
+
+        expected.sort();
+
+        assert_eq!(values, expected);
+        assert_eq!(bulk_price(input), 80);
+    }
+
+    #[test]
+    fn test_sides_with_holes() {
+        let input = r#"
+OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO
+        "#;
+
+        assert_eq!(bulk_price(input), 436);
+    }
+
+    #[test]
+    fn test_sides_e_shape() {
+        let input = r#"
+EEEEE
+EXXXX
+EEEEE
+EXXXX
+EEEEE
+        "#;
+
+        assert_eq!(bulk_price(input), 236);
+    }
+
+    #[test]
+    fn test_sides_diagonal_regions() {
+        let input = r#"
+AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA
+        "#;
+
+        let map = Map::parse(input);
+
+        let mut values = get_areas(&map)
+            .iter()
+            .map(|area| (area.area, area.sides))
+            .collect::<Vec<_>>();
+
+        values.sort();
+
+        // The B regions only touch diagonally, so they are counted separately
+        assert_eq!(values, vec![(4, 4), (4, 4), (28, 12)]);
+        assert_eq!(bulk_price(input), 368);
+    }
+
+    #[test]
+    fn test_sides_larger_example() {
+        let input = r#"
+RRRRIICCFF
+RRRRIICCCF
+VVRRRCCFFF
+VVRCCCJFFF
+VVVVCJJCFE
+VVIVCCJJEE
+VVIIICJJEE
+MIIIIIJJEE
+MIIISIJEEE
+MMMISSJEEE
+        "#;
+
+        assert_eq!(bulk_price(input), 1206);
+    }
+
+    #[test]
+    fn test_regions_geometry() {
+        let input = r#"
+AAAA
+BBCD
+BBCC
+EEEC
+        "#;
+
+        let regions = Regions::label(&Map::parse(input));
+
+        let plants = regions
+            .areas
+            .iter()
+            .map(|area| area.plant)
+            .collect::<String>();
+
+        assert_eq!(plants, "ABCDE");
+        assert_eq!(regions.labels[4..8], [1, 1, 2, 3]);
+
+        let c = &regions.areas[2];
+
+        assert_eq!((c.min, c.max), ((2, 1), (3, 3)));
+        assert_eq!(c.holes, 0);
+        assert_eq!(
+            c.outline,
+            vec![
+                (2, 1),
+                (3, 1),
+                (3, 2),
+                (4, 2),
+                (4, 4),
+                (3, 4),
+                (3, 3),
+                (2, 3)
+            ]
+        );
+
+        assert_eq!(
+            regions.areas[0].outline,
+            vec![(0, 0), (4, 0), (4, 1), (0, 1)]
+        );
+
+        assert_eq!(
+            regions.adjacent.iter().copied().collect::<Vec<_>>(),
+            vec![(0, 1), (0, 2), (0, 3), (1, 2), (1, 4), (2, 3), (2, 4)]
+        );
+    }
+
+    #[test]
+    fn test_regions_holes() {
+        let input = r#"
+OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO
+        "#;
+
+        let regions = Regions::label(&Map::parse(input));
+
+        assert_eq!(regions.areas[0].holes, 4);
+        assert_eq!(
+            regions.areas[0].outline,
+            vec![(0, 0), (5, 0), (5, 5), (0, 5)]
+        );
+        assert_eq!(
+            regions.adjacent.iter().copied().collect::<Vec<_>>(),
+            vec![(0, 1), (0, 2), (0, 3), (0, 4)]
+        );
+        assert!(regions.areas[1..].iter().all(|area| area.holes == 0));
+
+        // Holes only touching diagonally are separate holes
+        let input = r#"
+AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA
+        "#;
+
+        let regions = Regions::label(&Map::parse(input));
+
+        assert_eq!(regions.areas[0].holes, 2);
+        assert_eq!(regions.areas[0].sides, 12);
+        assert!(regions.adjacent.contains(&(0, 1)));
+        assert!(regions.adjacent.contains(&(0, 2)));
+        assert!(!regions.adjacent.contains(&(1, 2)));
+    }
+
+    #[test]
+    fn test_regions_outline_concave() {
+        let input = r#"
+XXX
+X.X
+X..
+        "#;
+
+        let regions = Regions::label(&Map::parse(input));
+
+        let x = &regions.areas[0];
+
+        assert_eq!(x.holes, 0);
+        assert_eq!(x.sides, 8);
+        assert_eq!(
+            x.outline,
+            vec![
+                (0, 0),
+                (3, 0),
+                (3, 2),
+                (2, 2),
+                (2, 1),
+                (1, 1),
+                (1, 3),
+                (0, 3)
+            ]
+        );
+    }
 }
@@ -1,25 +1,194 @@
 use std::fs;
 
+use itertools::Itertools;
+
+const COST_A: i128 = 3;
+const COST_B: i128 = 1;
+
+const PRIZE_OFFSET: i128 = 10_000_000_000_000;
+
+// Returns (g, x, y) with a * x + b * y = g, where g is the non-negative gcd of a and b
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return if a < 0 { (-a, -1, 0) } else { (a, 1, 0) };
+    }
+
+    let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+
+    (g, y, x - a.div_euclid(b) * y)
+}
+
+fn component((x, y): (i128, i128), axis: usize) -> i128 {
+    if axis == 0 {
+        x
+    } else {
+        y
+    }
+}
+
+// Returns the residue class (r, m) of all k with a * k = b modulo the positive modulus
+fn solve_congruence(a: i128, b: i128, modulus: i128) -> Option<(i128, i128)> {
+    let (g, x, _) = extended_gcd(a, modulus);
+
+    if b % g != 0 {
+        return None;
+    }
+
+    let m = modulus / g;
+
+    Some(((x * (b / g)).rem_euclid(m), m))
+}
+
+// Intersects two residue classes using the Chinese remainder theorem
+fn combine((r1, m1): (i128, i128), (r2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(m1, m2);
+
+    if (r2 - r1) % g != 0 {
+        return None;
+    }
+
+    let m = m1 / g * m2;
+    let k = ((r2 - r1) / g * p).rem_euclid(m2 / g);
+
+    Some(((r1 + m1 * k).rem_euclid(m), m))
+}
+
+// Narrows the range of k so that c + k * step stays non-negative
+fn constrain(range: &mut (Option<i128>, Option<i128>), c: i128, step: i128) -> bool {
+    match step.signum() {
+        1 => {
+            let lower = (-c).div_euclid(step) + ((-c).rem_euclid(step) != 0) as i128;
+            range.0 = Some(range.0.map_or(lower, |current| current.max(lower)));
+        }
+        -1 => {
+            let upper = c.div_euclid(-step);
+            range.1 = Some(range.1.map_or(upper, |current| current.min(upper)));
+        }
+        _ => return c >= 0,
+    }
+
+    true
+}
+
 struct ClawGame {
-    target: (isize, isize),
-    move_a: (isize, isize),
-    move_b: (isize, isize),
+    target: (i128, i128),
+    move_a: (i128, i128),
+    move_b: (i128, i128),
+    costs: (i128, i128),
+    press_limits: (Option<i128>, Option<i128>),
 }
 
 impl ClawGame {
-    fn min_token_cost(&self) -> Option<isize> {
+    // Finds the cheapest number of presses of both buttons which reaches the prize
+    fn presses(&self) -> Option<(i128, i128)> {
         let (ax, ay) = self.move_a;
         let (bx, by) = self.move_b;
         let (tx, ty) = self.target;
 
-        let nb = (ty * ax - tx * ay) / (by * ax - bx * ay);
-        let na = (tx - nb * bx) / ax;
+        let det = ax * by - ay * bx;
+
+        let (na, nb) = if det != 0 {
+            // The buttons are independent, so there is at most one solution
+            let na = tx * by - ty * bx;
+            let nb = ax * ty - ay * tx;
+
+            if na % det != 0 || nb % det != 0 {
+                return None;
+            }
+
+            (na / det, nb / det)
+        } else if ax != 0 || bx != 0 {
+            self.collinear_presses(ax, bx, tx)?
+        } else {
+            self.collinear_presses(ay, by, ty)?
+        };
 
         if (ax * na + bx * nb, ay * na + by * nb) != (tx, ty) {
             return None;
         }
 
-        Some(na * 3 + nb)
+        if na < 0 || nb < 0 {
+            return None;
+        }
+
+        let exceeds = |presses, limit: Option<i128>| limit.is_some_and(|limit| presses > limit);
+
+        if exceeds(na, self.press_limits.0) || exceeds(nb, self.press_limits.1) {
+            return None;
+        }
+
+        Some((na, nb))
+    }
+
+    // Both buttons move along the same line, so only one axis matters. All solutions of
+    // na * a + nb * b = target lie on a line of the form na = na0 + k * b / g and
+    // nb = nb0 - k * a / g, and the cost changes linearly with k, so the cheapest solution
+    // sits at one of the ends of the range of valid k.
+    fn collinear_presses(&self, a: i128, b: i128, target: i128) -> Option<(i128, i128)> {
+        if a == 0 && b == 0 {
+            return (target == 0).then_some((0, 0));
+        }
+
+        let (g, x, y) = extended_gcd(a, b);
+
+        if target % g != 0 {
+            return None;
+        }
+
+        let (na0, nb0) = (x * (target / g), y * (target / g));
+        let (step_a, step_b) = (b / g, -a / g);
+
+        let mut range = (None, None);
+
+        let mut valid = constrain(&mut range, na0, step_a) && constrain(&mut range, nb0, step_b);
+
+        if let Some(limit) = self.press_limits.0 {
+            valid = valid && constrain(&mut range, limit - na0, -step_a);
+        }
+
+        if let Some(limit) = self.press_limits.1 {
+            valid = valid && constrain(&mut range, limit - nb0, -step_b);
+        }
+
+        if !valid {
+            return None;
+        }
+
+        let presses = |k: i128| (na0 + k * step_a, nb0 + k * step_b);
+        let cost = |(na, nb): (i128, i128)| na * self.costs.0 + nb * self.costs.1;
+
+        match range {
+            (Some(lower), Some(upper)) if lower > upper => None,
+            (Some(lower), Some(upper)) => [presses(lower), presses(upper)]
+                .into_iter()
+                .min_by_key(|p| cost(*p)),
+            (Some(k), None) | (None, Some(k)) => Some(presses(k)),
+            (None, None) => None,
+        }
+    }
+}
+
+// Shortcuts for the two button machines from the puzzle
+#[cfg(test)]
+impl ClawGame {
+    fn min_token_cost(&self) -> Option<i128> {
+        let (na, nb) = self.presses()?;
+
+        Some(na * self.costs.0 + nb * self.costs.1)
+    }
+
+    fn with_press_limit(self, limit: i128) -> Self {
+        Self {
+            press_limits: (Some(limit), Some(limit)),
+            ..self
+        }
+    }
+
+    fn with_offset(self, offset: i128) -> Self {
+        Self {
+            target: (self.target.0 + offset, self.target.1 + offset),
+            ..self
+        }
     }
 
     fn parse(input: &str) -> Self {
@@ -71,19 +240,473 @@ impl ClawGame {
             target,
             move_a,
             move_b,
+            costs: (COST_A, COST_B),
+            press_limits: (None, None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Button {
+    name: char,
+    movement: (i128, i128),
+    cost: i128,
+    limit: Option<i128>,
+}
+
+impl Button {
+    // Parses lines like "Button C: X+5, Y-2", optionally followed by ", Cost=2" and
+    // ", Limit=100". Buttons without an explicit cost cost as much as B, except for A.
+    fn parse(input: &str) -> Self {
+        let Some((name, fields)) = input.trim().trim_start_matches("Button ").split_once(": ")
+        else {
+            panic!("Invalid button: {}", input);
+        };
+
+        let name = name.chars().next().unwrap();
+
+        let mut button = Button {
+            name,
+            movement: (0, 0),
+            cost: if name == 'A' { COST_A } else { COST_B },
+            limit: None,
+        };
+
+        for field in fields.split(", ") {
+            if let Some(value) = field.strip_prefix("X") {
+                button.movement.0 = value.trim_start_matches('+').parse().unwrap();
+            } else if let Some(value) = field.strip_prefix("Y") {
+                button.movement.1 = value.trim_start_matches('+').parse().unwrap();
+            } else if let Some(value) = field.strip_prefix("Cost=") {
+                button.cost = value.parse().unwrap();
+
+                if button.cost < 0 {
+                    panic!("Negative button cost: {}", field);
+                }
+            } else if let Some(value) = field.strip_prefix("Limit=") {
+                button.limit = Some(value.parse().unwrap());
+            } else {
+                panic!("Unknown button field: {}", field);
+            }
         }
+
+        button
     }
 }
 
+// A claw machine with any number of buttons. Machines with two buttons are solved in closed
+// form, larger ones search the lattice of integer solutions around the relaxed optimum.
+#[derive(Debug, Clone, PartialEq)]
+struct ClawMachine {
+    target: (i128, i128),
+    buttons: Vec<Button>,
+}
+
+impl ClawMachine {
+    fn parse(input: &str) -> Self {
+        let mut buttons = vec![];
+        let mut target = None;
+
+        for line in input.trim().lines() {
+            let line = line.trim();
+
+            if line.starts_with("Button ") {
+                buttons.push(Button::parse(line));
+            } else if let Some(prize) = line.strip_prefix("Prize: ") {
+                let Some((x, y)) = prize.split_once(", ") else {
+                    panic!("Invalid prize: {}", line);
+                };
+
+                target = Some((
+                    x.trim_start_matches("X=").parse().unwrap(),
+                    y.trim_start_matches("Y=").parse().unwrap(),
+                ));
+            } else {
+                panic!("Unknown line: {}", line);
+            }
+        }
+
+        Self {
+            target: target.expect("Missing prize"),
+            buttons,
+        }
+    }
+
+    fn with_press_limit(self, limit: i128) -> Self {
+        Self {
+            buttons: self
+                .buttons
+                .into_iter()
+                .map(|button| Button {
+                    limit: Some(button.limit.map_or(limit, |current| current.min(limit))),
+                    ..button
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    fn with_offset(self, offset: i128) -> Self {
+        Self {
+            target: (self.target.0 + offset, self.target.1 + offset),
+            ..self
+        }
+    }
+
+    fn min_token_cost(&self) -> Option<i128> {
+        let presses = self.presses()?;
+
+        Some(self.cost(&presses))
+    }
+
+    fn cost(&self, presses: &[i128]) -> i128 {
+        presses
+            .iter()
+            .zip(self.buttons.iter())
+            .map(|(presses, button)| presses * button.cost)
+            .sum()
+    }
+
+    fn movement(&self, axis: usize, index: usize) -> i128 {
+        component(self.buttons[index].movement, axis)
+    }
+
+    // Solves the given axes for the basic buttons, returning their presses as numerators
+    // over a common positive denominator
+    fn solve_basis(
+        &self,
+        basis: &[usize],
+        axes: &[usize],
+        rest: (i128, i128),
+    ) -> (Vec<i128>, i128) {
+        let m = |axis: usize, index: usize| self.movement(axis, index);
+        let t = |axis: usize| component(rest, axis);
+
+        let (nums, den) = match (basis, axes) {
+            (&[i], &[a]) => (vec![t(a)], m(a, i)),
+            (&[i, j], &[a, b]) => (
+                vec![
+                    t(a) * m(b, j) - t(b) * m(a, j),
+                    m(a, i) * t(b) - m(b, i) * t(a),
+                ],
+                m(a, i) * m(b, j) - m(b, i) * m(a, j),
+            ),
+            _ => (vec![], 1),
+        };
+
+        if den < 0 {
+            (nums.into_iter().map(|num| -num).collect(), -den)
+        } else {
+            (nums, den)
+        }
+    }
+
+    // Every set of linearly independent buttons that spans the movements, together with
+    // the axes it is solved on
+    fn bases(&self) -> Vec<(Vec<usize>, Vec<usize>)> {
+        let n = self.buttons.len();
+
+        let pairs: Vec<_> = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| vec![i, j]))
+            .filter(|basis| self.solve_basis(basis, &[0, 1], (0, 0)).1 != 0)
+            .collect();
+
+        if !pairs.is_empty() {
+            return pairs.into_iter().map(|basis| (basis, vec![0, 1])).collect();
+        }
+
+        let Some(axis) = (0..2).find(|axis| (0..n).any(|i| self.movement(*axis, i) != 0)) else {
+            return vec![(vec![], vec![])];
+        };
+
+        (0..n)
+            .filter(|i| self.movement(axis, *i) != 0)
+            .map(|i| (vec![i], vec![axis]))
+            .collect()
+    }
+
+    // Solves the relaxation over the reals. Its optimum sits on a vertex, where all but
+    // the basic buttons are pressed either never or as often as their limit allows.
+    fn relaxed_optimum(&self) -> Option<RelaxedOptimum> {
+        let mut best: Option<(RelaxedOptimum, (i128, i128))> = None;
+
+        for (basis, axes) in self.bases() {
+            let non_basic: Vec<_> = (0..self.buttons.len())
+                .filter(|i| !basis.contains(i))
+                .collect();
+
+            let bounds = non_basic.iter().map(|i| {
+                [Some(0), self.buttons[*i].limit]
+                    .into_iter()
+                    .flatten()
+                    .dedup()
+            });
+
+            for values in bounds.multi_cartesian_product() {
+                let mut presses = vec![(0, 1); self.buttons.len()];
+                let mut rest = self.target;
+
+                for (i, value) in non_basic.iter().zip(values) {
+                    presses[*i] = (value, 1);
+                    rest.0 -= self.buttons[*i].movement.0 * value;
+                    rest.1 -= self.buttons[*i].movement.1 * value;
+                }
+
+                let (nums, den) = self.solve_basis(&basis, &axes, rest);
+
+                for (i, num) in basis.iter().zip(nums) {
+                    presses[*i] = (num, den);
+                }
+
+                let within_limits =
+                    presses
+                        .iter()
+                        .zip(&self.buttons)
+                        .all(|((num, den), button)| {
+                            *num >= 0 && button.limit.is_none_or(|limit| *num <= limit * den)
+                        });
+
+                // Buttons are only solved on independent axes, so check the other one
+                let reaches = (0..2).all(|axis| {
+                    presses
+                        .iter()
+                        .zip(&self.buttons)
+                        .map(|((num, d), button)| {
+                            component(button.movement, axis) * num * (den / d)
+                        })
+                        .sum::<i128>()
+                        == component(self.target, axis) * den
+                });
+
+                if !within_limits || !reaches {
+                    continue;
+                }
+
+                let cost = (
+                    presses
+                        .iter()
+                        .zip(&self.buttons)
+                        .map(|((num, d), button)| button.cost * num * (den / d))
+                        .sum::<i128>(),
+                    den,
+                );
+
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, best)| cost.0 * best.1 < best.0 * cost.1)
+                {
+                    let optimum = RelaxedOptimum {
+                        basis: basis.clone(),
+                        axes: axes.clone(),
+                        presses,
+                    };
+                    best = Some((optimum, cost));
+                }
+            }
+        }
+
+        best.map(|(optimum, _)| optimum)
+    }
+
+    // Largest absolute subdeterminant of the movement matrix
+    fn max_subdeterminant(&self) -> i128 {
+        let n = self.buttons.len();
+
+        let entries = self
+            .buttons
+            .iter()
+            .flat_map(|button| [button.movement.0.abs(), button.movement.1.abs()]);
+
+        let determinants = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| self.solve_basis(&[i, j], &[0, 1], (0, 0)).1);
+
+        entries.chain(determinants).fold(1, i128::max)
+    }
+
+    // Finds the cheapest presses, which form an integer program with two equality
+    // constraints. By the proximity theorem of Cook et al. an optimal integer solution
+    // lies within n * Δ presses of the optimum of the relaxation, where n is the number of
+    // buttons and Δ the largest subdeterminant of the movement matrix. All non-basic
+    // buttons but one are enumerated within that window, the presses of the last one
+    // follow from a linear congruence and the basic buttons are then solved for directly.
+    fn presses(&self) -> Option<Vec<i128>> {
+        if let [a, b] = self.buttons.as_slice() {
+            let game = ClawGame {
+                target: self.target,
+                move_a: a.movement,
+                move_b: b.movement,
+                costs: (a.cost, b.cost),
+                press_limits: (a.limit, b.limit),
+            };
+
+            return game.presses().map(|(na, nb)| vec![na, nb]);
+        }
+
+        let optimum = self.relaxed_optimum()?;
+
+        let radius = self.buttons.len() as i128 * self.max_subdeterminant();
+
+        let windows: Vec<_> = (0..self.buttons.len())
+            .map(|i| {
+                let (num, den) = optimum.presses[i];
+                let value = num.div_euclid(den);
+                let upper = value + radius;
+
+                (
+                    (value - radius).max(0),
+                    self.buttons[i]
+                        .limit
+                        .map_or(upper, |limit| upper.min(limit)),
+                )
+            })
+            .collect();
+
+        let non_basic: Vec<_> = (0..self.buttons.len())
+            .filter(|i| !optimum.basis.contains(i))
+            .collect();
+
+        let mut best = None;
+        let mut presses = vec![0; self.buttons.len()];
+
+        self.search(&optimum, &windows, &non_basic, &mut presses, &mut best);
+
+        best
+    }
+
+    fn search(
+        &self,
+        optimum: &RelaxedOptimum,
+        windows: &[(i128, i128)],
+        non_basic: &[usize],
+        presses: &mut Vec<i128>,
+        best: &mut Option<Vec<i128>>,
+    ) {
+        match non_basic {
+            [] => self.complete(optimum, None, windows, presses, best),
+            [last] => self.complete(optimum, Some(*last), windows, presses, best),
+            [first, rest @ ..] => {
+                let (lower, upper) = windows[*first];
+
+                for n in lower..=upper {
+                    presses[*first] = n;
+                    self.search(optimum, windows, rest, presses, best);
+                }
+
+                presses[*first] = 0;
+            }
+        }
+    }
+
+    // Picks the presses of the last non-basic button, if any, and solves for the basic ones.
+    // The basic presses change linearly with the last button, so they are integral on a
+    // residue class and within limits on a range of its presses. The cost is linear along
+    // that range, which leaves only its two ends to check.
+    fn complete(
+        &self,
+        optimum: &RelaxedOptimum,
+        last: Option<usize>,
+        windows: &[(i128, i128)],
+        presses: &mut [i128],
+        best: &mut Option<Vec<i128>>,
+    ) {
+        let mut rest = self.target;
+
+        for (i, button) in self.buttons.iter().enumerate() {
+            if !optimum.basis.contains(&i) && Some(i) != last {
+                rest.0 -= button.movement.0 * presses[i];
+                rest.1 -= button.movement.1 * presses[i];
+            }
+        }
+
+        let (offsets, den) = self.solve_basis(&optimum.basis, &optimum.axes, rest);
+
+        let (steps, range) = match last {
+            Some(last) => {
+                let movement = self.buttons[last].movement;
+                let (steps, _) = self.solve_basis(&optimum.basis, &optimum.axes, movement);
+                (steps, windows[last])
+            }
+            None => (vec![0; offsets.len()], (0, 0)),
+        };
+
+        let mut range = (Some(range.0), Some(range.1));
+        let mut residue = Some((0, 1));
+        let mut valid = true;
+
+        // Basic presses are (offset - step * k) / den for k presses of the last button
+        for ((offset, step), i) in offsets.iter().zip(&steps).zip(&optimum.basis) {
+            residue = residue
+                .and_then(|residue| combine(residue, solve_congruence(*step, *offset, den)?));
+
+            valid = valid && constrain(&mut range, *offset, -step);
+
+            if let Some(limit) = self.buttons[*i].limit {
+                valid = valid && constrain(&mut range, limit * den - offset, *step);
+            }
+        }
+
+        let (Some(residue), true, (Some(lower), Some(upper))) = (residue, valid, range) else {
+            return;
+        };
+
+        let (r, m) = residue;
+        let candidates = [
+            lower + (r - lower).rem_euclid(m),
+            upper - (upper - r).rem_euclid(m),
+        ];
+
+        for k in candidates {
+            if k < lower || k > upper {
+                continue;
+            }
+
+            if let Some(last) = last {
+                presses[last] = k;
+            }
+
+            for ((offset, step), i) in offsets.iter().zip(&steps).zip(&optimum.basis) {
+                presses[*i] = (offset - step * k) / den;
+            }
+
+            let position = presses
+                .iter()
+                .zip(&self.buttons)
+                .fold((0, 0), |(x, y), (n, button)| {
+                    (x + n * button.movement.0, y + n * button.movement.1)
+                });
+
+            if position == self.target
+                && best
+                    .as_ref()
+                    .is_none_or(|best| self.cost(presses) < self.cost(best))
+            {
+                *best = Some(presses.to_vec());
+            }
+        }
+
+        for i in optimum.basis.iter().chain(last.iter()) {
+            presses[*i] = 0;
+        }
+    }
+}
+
+// Vertex of the relaxed problem, with presses given as fractions
+struct RelaxedOptimum {
+    basis: Vec<usize>,
+    axes: Vec<usize>,
+    presses: Vec<(i128, i128)>,
+}
+
 fn main() {
     let input = fs::read_to_string("./inputs/day13.txt").expect("Failed to read file");
 
     let mut result = 0;
 
-    for game_input in input.trim().split("\n\n") {
-        let game = ClawGame::parse(game_input.trim());
+    for machine_input in input.trim().split("\n\n") {
+        let machine = ClawMachine::parse(machine_input).with_press_limit(100);
 
-        if let Some(cost) = game.min_token_cost() {
+        if let Some(cost) = machine.min_token_cost() {
             result += cost;
         }
     }
@@ -92,15 +715,10 @@ fn main() {
 
     let mut result = 0;
 
-    for game_input in input.trim().split("\n\n") {
-        let mut game = ClawGame::parse(game_input.trim());
+    for machine_input in input.trim().split("\n\n") {
+        let machine = ClawMachine::parse(machine_input).with_offset(PRIZE_OFFSET);
 
-        game.target = (
-            game.target.0 + 10000000000000,
-            game.target.1 + 10000000000000,
-        );
-
-        if let Some(cost) = game.min_token_cost() {
+        if let Some(cost) = machine.min_token_cost() {
             result += cost;
         }
     }
@@ -110,7 +728,7 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use crate::ClawGame;
+    use super::*;
 
     #[test]
     fn test_game_1() {
@@ -163,4 +781,364 @@ Prize: X=18641, Y=10279
 
         assert_eq!(game.min_token_cost(), None);
     }
+
+    fn game(move_a: (i128, i128), move_b: (i128, i128), target: (i128, i128)) -> ClawGame {
+        ClawGame {
+            target,
+            move_a,
+            move_b,
+            costs: (COST_A, COST_B),
+            press_limits: (None, None),
+        }
+    }
+
+    #[test]
+    fn test_prize_offset() {
+        let input = r#"
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279
+        "#;
+
+        let costs = input
+            .trim()
+            .split("\n\n")
+            .map(|game_input| {
+                ClawGame::parse(game_input)
+                    .with_offset(PRIZE_OFFSET)
+                    .min_token_cost()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(costs[0], None);
+        assert_eq!(costs[2], None);
+        assert_eq!(costs.iter().flatten().sum::<i128>(), 875318608908);
+    }
+
+    #[test]
+    fn test_press_limit() {
+        let input = r#"
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+        "#;
+
+        let game = ClawGame::parse(input);
+
+        assert_eq!(game.presses(), Some((80, 40)));
+        assert_eq!(game.with_press_limit(50).min_token_cost(), None);
+
+        let game = ClawGame::parse(input).with_press_limit(100);
+        assert_eq!(game.min_token_cost(), Some(280));
+    }
+
+    #[test]
+    fn test_negative_presses() {
+        assert_eq!(game((1, 0), (0, 1), (-1, 1)).presses(), None);
+        assert_eq!(game((2, 1), (1, 1), (1, 2)).presses(), None);
+    }
+
+    #[test]
+    fn test_collinear_prefers_cheaper_button() {
+        // B covers half the distance of A for a third of the price
+        assert_eq!(game((2, 2), (1, 1), (10, 10)).presses(), Some((0, 10)));
+
+        // A covers four times the distance of B for three times the price
+        assert_eq!(game((4, 4), (1, 1), (10, 10)).presses(), Some((2, 2)));
+        assert_eq!(game((4, 4), (1, 1), (10, 10)).min_token_cost(), Some(8));
+    }
+
+    #[test]
+    fn test_collinear_gcd() {
+        assert_eq!(game((6, 6), (4, 4), (10, 10)).presses(), Some((1, 1)));
+        assert_eq!(game((6, 6), (4, 4), (11, 11)).presses(), None);
+        assert_eq!(game((6, 6), (4, 4), (2, 2)).presses(), None);
+    }
+
+    #[test]
+    fn test_collinear_off_line() {
+        assert_eq!(game((1, 2), (2, 4), (3, 5)).presses(), None);
+    }
+
+    #[test]
+    fn test_collinear_vertical() {
+        assert_eq!(game((0, 3), (0, 2), (0, 7)).presses(), Some((1, 2)));
+        assert_eq!(game((0, 3), (0, 2), (1, 7)).presses(), None);
+    }
+
+    #[test]
+    fn test_collinear_press_limit() {
+        let game = game((2, 2), (1, 1), (10, 10));
+
+        let limited = ClawGame {
+            press_limits: (Some(2), Some(2)),
+            ..game
+        };
+
+        assert_eq!(limited.presses(), None);
+        assert_eq!(game.with_press_limit(4).presses(), Some((3, 4)));
+    }
+
+    #[test]
+    fn test_collinear_huge() {
+        let game = game((5, 5), (3, 3), (0, 0)).with_offset(PRIZE_OFFSET);
+
+        // B is cheaper per unit of distance, so A only makes up the remainder
+        let (na, nb) = game.presses().unwrap();
+
+        assert_eq!(na * 5 + nb * 3, PRIZE_OFFSET);
+        assert!(na < 3);
+    }
+
+    #[test]
+    fn test_zero_buttons() {
+        assert_eq!(game((0, 0), (1, 1), (5, 5)).presses(), Some((0, 5)));
+        assert_eq!(game((0, 0), (0, 0), (0, 0)).presses(), Some((0, 0)));
+        assert_eq!(game((0, 0), (0, 0), (1, 0)).presses(), None);
+    }
+
+    #[test]
+    fn test_parse_machine() {
+        let input = r#"
+Button A: X+3, Y+3
+Button B: X+1, Y+2
+Button C: X-2, Y+1, Cost=5, Limit=10
+Prize: X=3, Y=3
+        "#;
+
+        let machine = ClawMachine::parse(input);
+
+        assert_eq!(machine.target, (3, 3));
+        assert_eq!(
+            machine.buttons,
+            vec![
+                Button {
+                    name: 'A',
+                    movement: (3, 3),
+                    cost: 3,
+                    limit: None,
+                },
+                Button {
+                    name: 'B',
+                    movement: (1, 2),
+                    cost: 1,
+                    limit: None,
+                },
+                Button {
+                    name: 'C',
+                    movement: (-2, 1),
+                    cost: 5,
+                    limit: Some(10),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_machine_two_buttons() {
+        let input = r#"
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279
+        "#;
+
+        let machines = input
+            .trim()
+            .split("\n\n")
+            .map(ClawMachine::parse)
+            .collect::<Vec<_>>();
+
+        let costs = machines
+            .iter()
+            .map(|machine| machine.clone().with_press_limit(100).min_token_cost())
+            .collect::<Vec<_>>();
+
+        assert_eq!(costs, vec![Some(280), None, Some(200), None]);
+
+        let result: i128 = machines
+            .iter()
+            .filter_map(|machine| machine.clone().with_offset(PRIZE_OFFSET).min_token_cost())
+            .sum();
+
+        assert_eq!(result, 875318608908);
+    }
+
+    #[test]
+    fn test_machine_extra_button() {
+        let input = r#"
+Button A: X+3, Y+3
+Button B: X+1, Y+2
+Button C: X+2, Y+1
+Prize: X=3, Y=3
+        "#;
+
+        let machine = ClawMachine::parse(input);
+        assert_eq!(machine.presses(), Some(vec![0, 1, 1]));
+        assert_eq!(machine.min_token_cost(), Some(2));
+
+        let machine = ClawMachine::parse(&input.replace("Y+1", "Y+1, Cost=5"));
+        assert_eq!(machine.presses(), Some(vec![1, 0, 0]));
+
+        let machine = ClawMachine::parse(&input.replace("Y+1", "Y+1, Limit=0"));
+        assert_eq!(machine.presses(), Some(vec![1, 0, 0]));
+
+        let machine = ClawMachine::parse(&input.replace("X=3, Y=3", "X=1, Y=1"));
+        assert_eq!(machine.presses(), None);
+    }
+
+    #[test]
+    fn test_machine_single_button() {
+        let input = r#"
+Button A: X+2, Y+4
+Prize: X=6, Y=12
+        "#;
+
+        assert_eq!(ClawMachine::parse(input).presses(), Some(vec![3]));
+
+        let input = input.replace("Y=12", "Y=11");
+        assert_eq!(ClawMachine::parse(&input).presses(), None);
+    }
+
+    #[test]
+    fn test_machine_brute_force() {
+        let input = r#"
+Button A: X+3, Y+1
+Button B: X+1, Y+3
+Button C: X+2, Y+2, Cost=2
+Button D: X+1, Y+0, Cost=2, Limit=3
+        "#;
+
+        for (tx, ty) in (0..15).flat_map(|x| (0..15).map(move |y| (x, y))) {
+            let machine = ClawMachine::parse(&format!("{}\nPrize: X={tx}, Y={ty}", input.trim()));
+
+            let mut expected = None;
+
+            for presses in (0..4).map(|_| 0..=15).multi_cartesian_product() {
+                if presses[3] > 3 {
+                    continue;
+                }
+
+                let position = presses
+                    .iter()
+                    .zip(machine.buttons.iter())
+                    .fold((0, 0), |(x, y), (n, button)| {
+                        (x + n * button.movement.0, y + n * button.movement.1)
+                    });
+
+                if position != (tx, ty) {
+                    continue;
+                }
+
+                let cost = machine.cost(&presses);
+                expected = Some(expected.map_or(cost, |best: i128| best.min(cost)));
+            }
+
+            assert_eq!(machine.min_token_cost(), expected, "Prize at ({tx}, {ty})");
+        }
+    }
+
+    #[test]
+    fn test_machine_cancelling_buttons() {
+        let input = r#"
+Button A: X+1, Y-1
+Button B: X-1, Y+1
+Button C: X+1, Y+1
+Prize: X=2, Y=2
+        "#;
+
+        let machine = ClawMachine::parse(input);
+        assert_eq!(machine.presses(), Some(vec![0, 0, 2]));
+        assert_eq!(machine.min_token_cost(), Some(2));
+
+        let machine = ClawMachine::parse(&input.replace("X=2", "X=4")).with_offset(PRIZE_OFFSET);
+        assert_eq!(machine.presses(), Some(vec![1, 0, PRIZE_OFFSET + 3]));
+
+        let machine = ClawMachine::parse(&input.replace("X=2", "X=3"));
+        assert_eq!(machine.presses(), None);
+    }
+
+    #[test]
+    fn test_machine_extra_button_offset() {
+        let input = r#"
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Button C: X+52, Y+132, Cost=7
+Prize: X=12748, Y=12176
+        "#;
+
+        let (na, nb) = game((26, 66), (67, 21), (12748, 12176))
+            .with_offset(PRIZE_OFFSET)
+            .presses()
+            .unwrap();
+
+        // C moves as far as two presses of A, so it only pays off when it is cheaper
+        let machine = ClawMachine::parse(input).with_offset(PRIZE_OFFSET);
+        assert_eq!(machine.presses(), Some(vec![na, nb, 0]));
+
+        let machine =
+            ClawMachine::parse(&input.replace("Cost=7", "Cost=5")).with_offset(PRIZE_OFFSET);
+        assert_eq!(machine.presses(), Some(vec![na % 2, nb, na / 2]));
+    }
+
+    #[test]
+    fn test_machine_brute_force_cancelling() {
+        let input = r#"
+Button A: X+1, Y-1
+Button B: X-1, Y+1, Cost=2
+Button C: X+1, Y+1, Cost=5
+Button D: X+2, Y+0, Cost=2, Limit=2
+        "#;
+
+        for (tx, ty) in (-4..=4).flat_map(|x| (-4..=4).map(move |y| (x, y))) {
+            let machine = ClawMachine::parse(&format!("{}\nPrize: X={tx}, Y={ty}", input.trim()));
+
+            let mut expected = None;
+
+            for presses in (0..4).map(|_| 0..=10).multi_cartesian_product() {
+                if presses[3] > 2 {
+                    continue;
+                }
+
+                let position = presses
+                    .iter()
+                    .zip(machine.buttons.iter())
+                    .fold((0, 0), |(x, y), (n, button)| {
+                        (x + n * button.movement.0, y + n * button.movement.1)
+                    });
+
+                if position != (tx, ty) {
+                    continue;
+                }
+
+                let cost = machine.cost(&presses);
+                expected = Some(expected.map_or(cost, |best: i128| best.min(cost)));
+            }
+
+            assert_eq!(machine.min_token_cost(), expected, "Prize at ({tx}, {ty})");
+        }
+    }
 }
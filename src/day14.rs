@@ -1,6 +1,8 @@
-use std::{collections::HashMap, fs};
-
-use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    env, fs,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 struct Robot {
@@ -11,28 +13,33 @@ struct Robot {
 }
 
 impl Robot {
+    #[cfg(test)]
     fn step(&mut self, world_width: usize, world_height: usize) {
-        let x = self.x as isize + self.vx;
-        let y = self.y as isize + self.vy;
-
-        let x = if x < 0 {
-            (world_width as isize + x) as usize
-        } else if x >= world_width as isize {
-            x as usize - world_width
-        } else {
-            x as usize
-        };
+        *self = self.at(1, world_width, world_height);
+    }
 
-        let y = if y < 0 {
-            (world_height as isize + y) as usize
-        } else if y >= world_height as isize {
-            y as usize - world_height
-        } else {
-            y as usize
-        };
+    // Positions repeat every `width` and `height` steps, so the time can be reduced before
+    // multiplying to keep arbitrarily large times from overflowing
+    fn at(&self, t: u64, world_width: usize, world_height: usize) -> Robot {
+        let tx = (t % world_width as u64) as i64;
+        let ty = (t % world_height as u64) as i64;
+
+        let x = (self.x as i64 + self.vx as i64 * tx).rem_euclid(world_width as i64);
+        let y = (self.y as i64 + self.vy as i64 * ty).rem_euclid(world_height as i64);
+
+        Robot {
+            x: x as usize,
+            y: y as usize,
+            ..*self
+        }
+    }
+
+    // Number of steps after which the robot is back at its starting position
+    fn period(&self, world_width: usize, world_height: usize) -> usize {
+        let x_period = world_width / gcd(self.vx.unsigned_abs(), world_width);
+        let y_period = world_height / gcd(self.vy.unsigned_abs(), world_height);
 
-        self.x = x;
-        self.y = y;
+        lcm(x_period, y_period)
     }
 
     fn parse(input: &str) -> Robot {
@@ -60,38 +67,110 @@ struct World {
 }
 
 impl World {
+    #[cfg(test)]
     fn step(&mut self) {
         for robot in &mut self.robots {
             robot.step(self.width, self.height);
         }
     }
 
-    fn safety_factor(&self) -> usize {
-        let q1 = self
-            .robots
-            .iter()
-            .filter(|robot| (robot.x < self.width / 2 && robot.y < self.height / 2))
-            .count();
+    fn at(&self, t: u64) -> World {
+        World {
+            width: self.width,
+            height: self.height,
+            robots: self
+                .robots
+                .iter()
+                .map(|robot| robot.at(t, self.width, self.height))
+                .collect(),
+        }
+    }
 
-        let q2 = self
-            .robots
+    // The whole world repeats once every robot is back at its start, which happens at the
+    // latest after lcm(width, height) steps
+    fn period(&self) -> usize {
+        self.robots
             .iter()
-            .filter(|robot| (robot.x > self.width / 2 && robot.y < self.height / 2))
-            .count();
+            .map(|robot| robot.period(self.width, self.height))
+            .fold(1, lcm)
+    }
+
+    fn quadrant(&self, x: usize, y: usize) -> Option<usize> {
+        let column = match x.cmp(&(self.width / 2)) {
+            Ordering::Less => 0,
+            Ordering::Equal => return None,
+            Ordering::Greater => 1,
+        };
+
+        let row = match y.cmp(&(self.height / 2)) {
+            Ordering::Less => 0,
+            Ordering::Equal => return None,
+            Ordering::Greater => 1,
+        };
+
+        Some(row * 2 + column)
+    }
+
+    fn safety_factor(&self) -> usize {
+        let mut quadrants = [0; 4];
+
+        for robot in &self.robots {
+            if let Some(quadrant) = self.quadrant(robot.x, robot.y) {
+                quadrants[quadrant] += 1;
+            }
+        }
 
-        let q3 = self
+        quadrants.iter().product()
+    }
+
+    // Safety factor after every step of one period. The horizontal and vertical positions of
+    // every robot only depend on the step modulo the width and height, so both are
+    // precomputed once instead of simulating the whole period.
+    fn safety_factors(&self) -> Vec<usize> {
+        let columns: Vec<Vec<usize>> = self
             .robots
             .iter()
-            .filter(|robot| (robot.x < self.width / 2 && robot.y > self.height / 2))
-            .count();
+            .map(|robot| {
+                (0..self.width)
+                    .map(|t| robot.at(t as u64, self.width, self.height).x)
+                    .collect()
+            })
+            .collect();
 
-        let q4 = self
+        let rows: Vec<Vec<usize>> = self
             .robots
             .iter()
-            .filter(|robot| (robot.x > self.width / 2 && robot.y > self.height / 2))
-            .count();
+            .map(|robot| {
+                (0..self.height)
+                    .map(|t| robot.at(t as u64, self.width, self.height).y)
+                    .collect()
+            })
+            .collect();
 
-        q1 * q2 * q3 * q4
+        (0..self.period())
+            .map(|t| {
+                let mut quadrants = [0; 4];
+
+                for (column, row) in columns.iter().zip(rows.iter()) {
+                    let x = column[t % self.width];
+                    let y = row[t % self.height];
+
+                    if let Some(quadrant) = self.quadrant(x, y) {
+                        quadrants[quadrant] += 1;
+                    }
+                }
+
+                quadrants.iter().product()
+            })
+            .collect()
+    }
+
+    fn lowest_safety_factor(&self) -> (usize, usize) {
+        self.safety_factors()
+            .into_iter()
+            .enumerate()
+            .min_by_key(|(_, factor)| *factor)
+            .unwrap()
     }
 
     fn robots_at(&self, x: usize, y: usize) -> usize {
@@ -101,28 +180,246 @@ impl World {
             .count()
     }
 
+    fn occupancy(&self) -> HashMap<(usize, usize), usize> {
+        let mut result = HashMap::new();
+
+        for robot in &self.robots {
+            *result.entry((robot.x, robot.y)).or_insert(0) += 1;
+        }
+
+        result
+    }
+
+    // Number of tiles holding exactly as many robots as the index, starting with empty tiles
+    fn occupancy_histogram(&self) -> Vec<usize> {
+        let occupancy = self.occupancy();
+
+        let mut result = vec![0; occupancy.values().max().unwrap_or(&0) + 1];
+
+        result[0] = self.width * self.height - occupancy.len();
+
+        for count in occupancy.values() {
+            result[*count] += 1;
+        }
+
+        result
+    }
+
+    fn neighbours(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+
+        if x > 0 {
+            result.push((x - 1, y));
+        }
+
+        if x + 1 < self.width {
+            result.push((x + 1, y));
+        }
+
+        if y > 0 {
+            result.push((x, y - 1));
+        }
+
+        if y + 1 < self.height {
+            result.push((x, y + 1));
+        }
+
+        result
+    }
+
+    // Number of robots in the largest group of orthogonally adjacent occupied tiles
+    fn largest_cluster(&self) -> usize {
+        let occupancy = self.occupancy();
+        let mut visited = HashSet::new();
+        let mut result = 0;
+
+        for start in occupancy.keys() {
+            if !visited.insert(*start) {
+                continue;
+            }
+
+            let mut frontier = vec![*start];
+            let mut size = 0;
+
+            while let Some(position) = frontier.pop() {
+                size += occupancy[&position];
+
+                for neighbour in self.neighbours(position) {
+                    if occupancy.contains_key(&neighbour) && visited.insert(neighbour) {
+                        frontier.push(neighbour);
+                    }
+                }
+            }
+
+            result = result.max(size);
+        }
+
+        result
+    }
+
+    fn robots_with_neighbours(&self) -> usize {
+        let occupancy = self.occupancy();
+
+        self.robots
+            .iter()
+            .filter(|robot| {
+                self.neighbours((robot.x, robot.y))
+                    .iter()
+                    .any(|neighbour| occupancy.contains_key(neighbour))
+            })
+            .count()
+    }
+
+    // Share of robots which have a robot at their mirrored position across the vertical axis
+    fn symmetry(&self) -> f64 {
+        if self.robots.is_empty() {
+            return 0.0;
+        }
+
+        let occupancy = self.occupancy();
+
+        let mirrored = self
+            .robots
+            .iter()
+            .filter(|robot| occupancy.contains_key(&(self.width - 1 - robot.x, robot.y)))
+            .count();
+
+        mirrored as f64 / self.robots.len() as f64
+    }
+
+    fn metrics(&self, t: u64) -> Metrics {
+        let world = self.at(t);
+        let histogram = world.occupancy_histogram();
+
+        Metrics {
+            step: t,
+            safety_factor: world.safety_factor(),
+            occupied_tiles: histogram[1..].iter().sum(),
+            stacked_tiles: histogram.iter().skip(2).sum(),
+            largest_cluster: world.largest_cluster(),
+            robots_with_neighbours: world.robots_with_neighbours(),
+            symmetry: world.symmetry(),
+        }
+    }
+
+    // Exports the metrics for every step of one period as CSV
+    fn export_csv(&self) -> String {
+        let mut result = String::from(Metrics::CSV_HEADER);
+        result.push('\n');
+
+        for t in 0..self.period() {
+            result.push_str(&self.metrics(t as u64).to_csv());
+            result.push('\n');
+        }
+
+        result
+    }
+
     fn print(&self) {
+        print!("{}", self.render());
+    }
+
+    fn render(&self) -> String {
         let filled = self
             .robots
             .iter()
-            .chunk_by(|robot| (robot.x, robot.y))
-            .into_iter()
-            .map(|((x, y), chunk)| ((x, y), chunk.count()))
-            .collect::<HashMap<(usize, usize), usize>>();
+            .map(|robot| (robot.x, robot.y))
+            .collect::<HashSet<(usize, usize)>>();
+
+        let mut result = String::new();
 
         for y in 0..self.height {
             for x in 0..self.width {
-                if let Some(count) = filled.get(&(x, y)) {
-                    print!("■");
+                if filled.contains(&(x, y)) {
+                    result.push('■');
                 } else {
-                    print!(" ");
+                    result.push(' ');
                 }
             }
-            println!();
+            result.push('\n');
         }
+
+        result
+    }
+
+    // Finds the step within one period of an axis at which the robots are packed most
+    // tightly along that axis
+    fn tightest_step(&self, period: usize, position: impl Fn(&Robot, usize) -> usize) -> usize {
+        (0..period)
+            .min_by_key(|step| spread(self.robots.iter().map(|robot| position(robot, *step))))
+            .unwrap()
+    }
+
+    // The robots move independently along both axes, so the horizontal positions repeat
+    // every `width` steps and the vertical ones every `height` steps. The picture shows up
+    // when both axes are tightest at the same time, which is found via the CRT.
+    fn find_christmas_tree(&self) -> Option<usize> {
+        let at = |robot: &Robot, step: usize| robot.at(step as u64, self.width, self.height);
+
+        let x_step = self.tightest_step(self.width, |robot, step| at(robot, step).x);
+        let y_step = self.tightest_step(self.height, |robot, step| at(robot, step).y);
+
+        chinese_remainder((x_step, self.width), (y_step, self.height))
+    }
+}
+
+// Variance of the values, scaled by the squared number of values to stay in integers
+fn spread(values: impl Iterator<Item = usize>) -> usize {
+    let (count, sum, sum_of_squares) = values.fold((0, 0, 0), |(count, sum, squares), value| {
+        (count + 1, sum + value, squares + value * value)
+    });
+
+    count * sum_of_squares - sum * sum
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Metrics {
+    step: u64,
+    safety_factor: usize,
+    occupied_tiles: usize,
+    stacked_tiles: usize,
+    largest_cluster: usize,
+    robots_with_neighbours: usize,
+    symmetry: f64,
+}
+
+impl Metrics {
+    const CSV_HEADER: &'static str =
+        "step,safety_factor,occupied_tiles,stacked_tiles,largest_cluster,robots_with_neighbours,symmetry";
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{:.4}",
+            self.step,
+            self.safety_factor,
+            self.occupied_tiles,
+            self.stacked_tiles,
+            self.largest_cluster,
+            self.robots_with_neighbours,
+            self.symmetry
+        )
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
 }
 
+fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+
+// Finds the smallest number that leaves both given remainders
+fn chinese_remainder((a, m): (usize, usize), (b, n): (usize, usize)) -> Option<usize> {
+    (0..n)
+        .map(|k| a + k * m)
+        .find(|candidate| candidate % n == b)
+}
+
 fn main() {
     let input = fs::read_to_string("./inputs/day14.txt").expect("Failed to read file");
 
@@ -137,32 +434,28 @@ fn main() {
         robots,
     };
 
-    let mut w_part_1 = world.clone();
-
-    for _ in 0..100 {
-        w_part_1.step();
-    }
-
-    let result = w_part_1.safety_factor();
+    let result = world.at(100).safety_factor();
 
     println!("Result (Part 1): {result}");
 
-    let mut w_part_2 = world.clone();
+    let result = world
+        .find_christmas_tree()
+        .expect("The robots never line up");
 
-    // I did not code an automatic way to get the result for part 2, just looked at the output
+    world.at(result as u64).print();
 
-    for i in 1..1000000000 {
-        w_part_2.step();
+    let (step, factor) = world.lowest_safety_factor();
 
-        // I noticed this pattern after looking at the output for a while
-        if i % 101 != 2 {
-            continue;
-        }
+    println!(
+        "Lowest safety factor within a period of {} steps: {factor} (step {step})",
+        world.period()
+    );
+
+    println!("Result (Part 2): {result}");
 
-        println!("-------------------");
-        println!("Step {i}");
-        println!("");
-        w_part_2.print();
+    if let Some(path) = env::args().skip_while(|arg| arg != "--csv").nth(1) {
+        fs::write(&path, world.export_csv()).expect("Failed to write file");
+        println!("Wrote metrics for one period to {path}");
     }
 }
 
@@ -280,4 +573,183 @@ mod tests {
         assert_eq!(w.safety_factor(), 12);
         assert_eq!(w.safety_factor(), 12);
     }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(chinese_remainder((2, 3), (3, 5)), Some(8));
+        assert_eq!(chinese_remainder((0, 101), (0, 103)), Some(0));
+        assert_eq!(chinese_remainder((1, 4), (0, 6)), None);
+    }
+
+    #[test]
+    fn test_find_christmas_tree() {
+        let width: isize = 11;
+        let height: isize = 7;
+        let target: isize = 52;
+
+        // Every robot ends up in a small block in the middle at the target step
+        let robots = (0..40isize)
+            .map(|i| {
+                let vx = i % 10 + 1;
+                let vy = -(i % 6 + 1);
+                let x = 4 + i % 3;
+                let y = 2 + i % 2;
+
+                Robot {
+                    x: (x - vx * target).rem_euclid(width) as usize,
+                    y: (y - vy * target).rem_euclid(height) as usize,
+                    vx,
+                    vy,
+                }
+            })
+            .collect();
+
+        let mut world = World {
+            width: width as usize,
+            height: height as usize,
+            robots,
+        };
+
+        assert_eq!(world.find_christmas_tree(), Some(target as usize));
+
+        for _ in 0..target {
+            world.step();
+        }
+
+        assert_eq!(
+            world.render(),
+            [
+                "           ",
+                "           ",
+                "    ■■■    ",
+                "    ■■■    ",
+                "           ",
+                "           ",
+                "           ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    fn example() -> World {
+        let input = r#"
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3
+        "#;
+
+        World {
+            width: 11,
+            height: 7,
+            robots: input.trim().lines().map(Robot::parse).collect(),
+        }
+    }
+
+    #[test]
+    fn test_at() {
+        let world = example();
+        let mut stepped = world.clone();
+
+        for t in 0..100 {
+            assert_eq!(world.at(t).robots, stepped.robots);
+            stepped.step();
+        }
+
+        assert_eq!(world.at(100).safety_factor(), 12);
+
+        let t = 1_000_000_000_000;
+        let period = world.period() as u64;
+
+        assert_eq!(world.at(t).robots, world.at(t % period).robots);
+    }
+
+    #[test]
+    fn test_period() {
+        let world = example();
+
+        assert_eq!(world.period(), 77);
+        assert_eq!(world.at(77).robots, world.robots);
+
+        let robot = Robot::parse("p=2,4 v=0,7");
+        assert_eq!(robot.period(11, 7), 1);
+
+        let robot = Robot::parse("p=2,4 v=11,1");
+        assert_eq!(robot.period(11, 7), 7);
+    }
+
+    #[test]
+    fn test_safety_factors() {
+        let world = example();
+
+        let factors = world.safety_factors();
+
+        assert_eq!(factors.len(), 77);
+        assert_eq!(factors[100 % 77], 12);
+
+        for (t, factor) in factors.iter().enumerate() {
+            assert_eq!(*factor, world.at(t as u64).safety_factor());
+        }
+
+        let (step, factor) = world.lowest_safety_factor();
+        assert_eq!(factor, *factors.iter().min().unwrap());
+        assert_eq!(factors[step], factor);
+    }
+
+    #[test]
+    fn test_analytics() {
+        let world = World {
+            width: 7,
+            height: 5,
+            robots: [(0, 0), (1, 0), (1, 0), (1, 1), (5, 0), (3, 3), (6, 4)]
+                .iter()
+                .map(|(x, y)| Robot {
+                    x: *x,
+                    y: *y,
+                    vx: 0,
+                    vy: 0,
+                })
+                .collect(),
+        };
+
+        assert_eq!(world.occupancy_histogram(), vec![29, 5, 1]);
+        assert_eq!(world.largest_cluster(), 4);
+        assert_eq!(world.robots_with_neighbours(), 4);
+        assert_eq!(world.symmetry(), 4.0 / 7.0);
+
+        assert_eq!(
+            world.metrics(0),
+            Metrics {
+                step: 0,
+                safety_factor: 0,
+                occupied_tiles: 6,
+                stacked_tiles: 1,
+                largest_cluster: 4,
+                robots_with_neighbours: 4,
+                symmetry: 4.0 / 7.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let world = example();
+
+        let csv = world.export_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 78);
+        assert_eq!(lines[0], Metrics::CSV_HEADER);
+        assert_eq!(lines[1], world.metrics(0).to_csv());
+        assert_eq!(lines[1], "0,0,11,1,3,7,0.0000");
+    }
 }
@@ -1,78 +1,61 @@
-use std::{collections::VecDeque, fs};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    env, fs,
+    io::{self, Read},
+    process,
+};
 
 fn shift_right(row: &mut Vec<Option<Object>>, start: usize) -> bool {
     match row[start] {
-        Some(Object::Box) => {}
+        Some(Object::Box | Object::BoxLeft | Object::BoxRight) => {}
         Some(Object::Wall) => return false,
         None => return true,
     }
 
-    let mut current: Option<Object> = row[start];
-    row[start] = None;
-
     for i in start + 1..row.len() {
-        if current.is_none() {
-            break;
-        }
-
         if row[i] == Some(Object::Wall) {
             return false;
         }
 
         if row[i].is_none() {
-            row[i] = current;
-            current = None;
-            break;
+            row[start..=i].rotate_right(1);
+            return true;
         }
     }
 
-    if current.is_some() {
-        return false;
-    }
-
-    true
+    false
 }
 
 fn shift_left(row: &mut Vec<Option<Object>>, start: usize) -> bool {
     match row[start] {
-        Some(Object::Box) => {}
+        Some(Object::Box | Object::BoxLeft | Object::BoxRight) => {}
         Some(Object::Wall) => return false,
         None => return true,
     }
 
-    let mut current: Option<Object> = row[start];
-    row[start] = None;
-
     for i in (0..start).rev() {
-        if current.is_none() {
-            break;
-        }
-
         if row[i] == Some(Object::Wall) {
             return false;
         }
 
         if row[i].is_none() {
-            row[i] = current;
-            current = None;
-            break;
+            row[i..=start].rotate_left(1);
+            return true;
         }
     }
 
-    if current.is_some() {
-        return false;
-    }
-
-    true
+    false
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Object {
     Wall,
     Box,
+    BoxLeft,
+    BoxRight,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 struct Map {
     tiles: Vec<Vec<Option<Object>>>,
 }
@@ -87,24 +70,34 @@ impl Map {
     }
 
     fn print(&self, robot: Option<(usize, usize)>) {
+        println!("{}", self.render(robot));
+    }
+
+    fn render(&self, robot: Option<(usize, usize)>) -> String {
+        let mut result = String::new();
+
         for y in 0..self.height() {
             for x in 0..self.width() {
                 if let Some(robot) = robot {
                     if robot.0 == x && robot.1 == y {
-                        print!("@");
+                        result.push('@');
                         continue;
                     }
                 }
 
-                match self.tiles[y][x] {
-                    None => print!("."),
-                    Some(Object::Box) => print!("O"),
-                    Some(Object::Wall) => print!("#"),
-                }
+                result.push(match self.tiles[y][x] {
+                    None => '.',
+                    Some(Object::Box) => 'O',
+                    Some(Object::BoxLeft) => '[',
+                    Some(Object::BoxRight) => ']',
+                    Some(Object::Wall) => '#',
+                });
             }
 
-            println!();
+            result.push('\n');
         }
+
+        result
     }
 
     fn parse(input: &str) -> (Self, (usize, usize)) {
@@ -123,6 +116,8 @@ impl Map {
                 match c {
                     '#' => row.push(Some(Object::Wall)),
                     'O' => row.push(Some(Object::Box)),
+                    '[' => row.push(Some(Object::BoxLeft)),
+                    ']' => row.push(Some(Object::BoxRight)),
                     '@' => {
                         row.push(None);
                         robot = (x, y);
@@ -138,6 +133,24 @@ impl Map {
         (Map { tiles }, robot)
     }
 
+    // Doubles the map horizontally, turning every box into a two-cell wide box
+    fn widen(&self) -> Self {
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .flat_map(|tile| match tile {
+                        Some(Object::Box) => [Some(Object::BoxLeft), Some(Object::BoxRight)],
+                        tile => [*tile, *tile],
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Map { tiles }
+    }
+
     fn get_row(&self, index: usize) -> Vec<Option<Object>> {
         self.tiles[index].clone()
     }
@@ -188,7 +201,60 @@ impl Map {
         false
     }
 
+    // Pushes everything starting at the given position one tile up or down. Wide boxes can
+    // push two boxes at once, so the affected boxes form a tree which is moved atomically.
+    fn try_push_vertical(&mut self, start: (usize, usize), dy: isize) -> bool {
+        let mut frontier = vec![start];
+        let mut affected = HashSet::new();
+
+        while let Some((x, y)) = frontier.pop() {
+            if affected.contains(&(x, y)) {
+                continue;
+            }
+
+            let ny = y as isize + dy;
+
+            match self.tiles[y][x] {
+                None => continue,
+                Some(Object::Wall) => return false,
+                Some(Object::Box) => {}
+                Some(Object::BoxLeft) => frontier.push((x + 1, y)),
+                Some(Object::BoxRight) => frontier.push((x - 1, y)),
+            }
+
+            if ny < 0 || ny as usize >= self.height() {
+                return false;
+            }
+
+            affected.insert((x, y));
+            frontier.push((x, ny as usize));
+        }
+
+        let mut affected: Vec<(usize, usize)> = affected.into_iter().collect();
+
+        // Move the tiles furthest in the pushing direction first
+        affected.sort_by_key(|(_, y)| *y as isize * -dy);
+
+        for (x, y) in affected {
+            let ny = (y as isize + dy) as usize;
+            self.tiles[ny][x] = self.tiles[y][x].take();
+        }
+
+        true
+    }
+
+    fn is_wide_box(&self, (x, y): (usize, usize)) -> bool {
+        matches!(
+            self.tiles[y][x],
+            Some(Object::BoxLeft) | Some(Object::BoxRight)
+        )
+    }
+
     fn try_shift_down(&mut self, start: (usize, usize)) -> bool {
+        if self.is_wide_box(start) {
+            return self.try_push_vertical(start, 1);
+        }
+
         let mut column = self.get_column(start.0);
 
         let should_update = shift_right(&mut column, start.1);
@@ -202,6 +268,10 @@ impl Map {
     }
 
     fn try_shift_up(&mut self, start: (usize, usize)) -> bool {
+        if self.is_wide_box(start) {
+            return self.try_push_vertical(start, -1);
+        }
+
         let mut column = self.get_column(start.0);
 
         let should_update = shift_left(&mut column, start.1);
@@ -219,7 +289,8 @@ impl Map {
 
         for (y, row) in self.tiles.iter().enumerate() {
             for (x, tile) in row.iter().enumerate() {
-                if let Some(Object::Box) = tile {
+                // Wide boxes are measured from their left edge
+                if let Some(Object::Box | Object::BoxLeft) = tile {
                     result += 100 * y + x;
                 }
             }
@@ -237,6 +308,17 @@ enum Move {
     Right,
 }
 
+impl From<Move> for char {
+    fn from(value: Move) -> Self {
+        match value {
+            Move::Up => '^',
+            Move::Down => 'v',
+            Move::Left => '<',
+            Move::Right => '>',
+        }
+    }
+}
+
 impl TryFrom<char> for Move {
     type Error = ();
 
@@ -251,7 +333,7 @@ impl TryFrom<char> for Move {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Game {
     map: Map,
     instructions: VecDeque<Move>,
@@ -289,11 +371,16 @@ impl Game {
         }
     }
 
+    #[cfg(test)]
     fn step(&mut self) {
         let Some(instruction) = self.instructions.pop_front() else {
             return;
         };
 
+        self.apply(instruction);
+    }
+
+    fn apply(&mut self, instruction: Move) {
         match instruction {
             Move::Right => {
                 let next_pos = (self.robot.0 + 1, self.robot.1);
@@ -322,6 +409,15 @@ impl Game {
         }
     }
 
+    fn widen(self) -> Game {
+        Game {
+            map: self.map.widen(),
+            instructions: self.instructions,
+            robot: (self.robot.0 * 2, self.robot.1),
+        }
+    }
+
+    #[cfg(test)]
     fn run(&mut self) {
         while self.instructions.len() > 0 {
             self.step();
@@ -333,16 +429,576 @@ impl Game {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Shape {
+    Robot,
+    Box,
+    WideBox,
+    Piece(char),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Piece {
+    shape: Shape,
+    cells: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cell {
+    Wall,
+    Piece(usize),
+}
+
+// Maps every object taking part in a push to the objects it pushes directly
+type PushGraph = HashMap<usize, Vec<usize>>;
+
+// A warehouse with arbitrarily shaped objects, which are identified by their index into
+// `pieces`. Robots are objects too, so they can push each other around.
+#[derive(Debug, Clone)]
+struct Warehouse {
+    cells: Vec<Vec<Option<Cell>>>,
+    pieces: Vec<Piece>,
+    robots: Vec<usize>,
+    instructions: VecDeque<Move>,
+    turn: usize,
+}
+
+impl Warehouse {
+    // Besides the regular tiles, every other character marks a cell of a polyomino. Adjacent
+    // cells with the same character belong to the same object.
+    fn parse(input: &str) -> Self {
+        let input = input.trim();
+
+        let mut split = input.split("\n\n");
+        let tile_input = split.next().unwrap();
+
+        let labels: Vec<Vec<char>> = tile_input
+            .lines()
+            .map(|line| line.trim().chars().collect())
+            .collect();
+
+        let mut cells: Vec<Vec<Option<Cell>>> = labels
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|c| if *c == '#' { Some(Cell::Wall) } else { None })
+                    .collect()
+            })
+            .collect();
+
+        let mut pieces: Vec<Piece> = vec![];
+        let mut robots = vec![];
+
+        for y in 0..labels.len() {
+            for x in 0..labels[y].len() {
+                if cells[y][x].is_some() {
+                    continue;
+                }
+
+                let piece = match labels[y][x] {
+                    '.' => continue,
+                    '@' => {
+                        robots.push(pieces.len());
+
+                        Piece {
+                            shape: Shape::Robot,
+                            cells: vec![(x, y)],
+                        }
+                    }
+                    'O' => Piece {
+                        shape: Shape::Box,
+                        cells: vec![(x, y)],
+                    },
+                    '[' => {
+                        if labels[y].get(x + 1) != Some(&']') {
+                            panic!("Unclosed box at ({x}, {y})");
+                        }
+
+                        Piece {
+                            shape: Shape::WideBox,
+                            cells: vec![(x, y), (x + 1, y)],
+                        }
+                    }
+                    ']' => panic!("Unopened box at ({x}, {y})"),
+                    c if c.is_alphanumeric() => {
+                        let mut piece_cells = vec![];
+                        let mut frontier = vec![(x, y)];
+                        let mut visited = HashSet::from([(x, y)]);
+
+                        while let Some((x, y)) = frontier.pop() {
+                            piece_cells.push((x, y));
+
+                            let neighbours = [
+                                (x.wrapping_sub(1), y),
+                                (x + 1, y),
+                                (x, y.wrapping_sub(1)),
+                                (x, y + 1),
+                            ];
+
+                            for (nx, ny) in neighbours {
+                                let Some(label) = labels.get(ny).and_then(|row| row.get(nx)) else {
+                                    continue;
+                                };
+
+                                if *label == c && visited.insert((nx, ny)) {
+                                    frontier.push((nx, ny));
+                                }
+                            }
+                        }
+
+                        piece_cells.sort_by_key(|(x, y)| (*y, *x));
+
+                        Piece {
+                            shape: Shape::Piece(c),
+                            cells: piece_cells,
+                        }
+                    }
+                    c => panic!("Unknown character: {}", c),
+                };
+
+                for (x, y) in piece.cells.iter() {
+                    cells[*y][*x] = Some(Cell::Piece(pieces.len()));
+                }
+
+                pieces.push(piece);
+            }
+        }
+
+        if robots.is_empty() {
+            panic!("Warehouse without robots");
+        }
+
+        let mut instructions: VecDeque<Move> = VecDeque::new();
+
+        for instructions_input in split {
+            for c in instructions_input.chars() {
+                if c.is_whitespace() {
+                    continue;
+                }
+
+                if let Ok(instruction) = Move::try_from(c) {
+                    instructions.push_back(instruction);
+                } else {
+                    panic!("Unknown instruction: {}", c);
+                }
+            }
+        }
+
+        Warehouse {
+            cells,
+            pieces,
+            robots,
+            instructions,
+            turn: 0,
+        }
+    }
+
+    #[cfg(test)]
+    fn render(&self) -> String {
+        let mut result = String::new();
+
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                result.push(match cell {
+                    None => '.',
+                    Some(Cell::Wall) => '#',
+                    Some(Cell::Piece(id)) => {
+                        let piece = &self.pieces[*id];
+
+                        match piece.shape {
+                            Shape::Robot => '@',
+                            Shape::Box => 'O',
+                            Shape::WideBox if piece.cells[0] == (x, y) => '[',
+                            Shape::WideBox => ']',
+                            Shape::Piece(c) => c,
+                        }
+                    }
+                });
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+
+    fn neighbour(&self, (x, y): (usize, usize), direction: Move) -> Option<(usize, usize)> {
+        let (x, y) = match direction {
+            Move::Up => (x, y.checked_sub(1)?),
+            Move::Down => (x, y + 1),
+            Move::Left => (x.checked_sub(1)?, y),
+            Move::Right => (x + 1, y),
+        };
+
+        if y >= self.cells.len() || x >= self.cells[y].len() {
+            return None;
+        }
+
+        Some((x, y))
+    }
+
+    // Collects every object that has to move when the given object is pushed one tile in the
+    // given direction, or None if one of them would run into a wall
+    fn resolve_push(&self, id: usize, direction: Move) -> Option<PushGraph> {
+        let mut graph = PushGraph::from([(id, vec![])]);
+        let mut queue = VecDeque::from([id]);
+
+        while let Some(current) = queue.pop_front() {
+            for cell in self.pieces[current].cells.iter() {
+                let (x, y) = self.neighbour(*cell, direction)?;
+
+                let other = match self.cells[y][x] {
+                    None => continue,
+                    Some(Cell::Wall) => return None,
+                    Some(Cell::Piece(other)) if other == current => continue,
+                    Some(Cell::Piece(other)) => other,
+                };
+
+                let dependencies = graph.get_mut(&current).unwrap();
+
+                if !dependencies.contains(&other) {
+                    dependencies.push(other);
+                }
+
+                if let Entry::Vacant(entry) = graph.entry(other) {
+                    entry.insert(vec![]);
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        Some(graph)
+    }
+
+    fn push(&mut self, id: usize, direction: Move) -> bool {
+        let Some(graph) = self.resolve_push(id, direction) else {
+            return false;
+        };
+
+        // Lift all affected objects off the grid first, so objects can move into cells which
+        // were just vacated by the objects they push
+        for id in graph.keys() {
+            for (x, y) in self.pieces[*id].cells.iter() {
+                self.cells[*y][*x] = None;
+            }
+        }
+
+        for id in graph.keys() {
+            for i in 0..self.pieces[*id].cells.len() {
+                let (x, y) = self
+                    .neighbour(self.pieces[*id].cells[i], direction)
+                    .unwrap();
+
+                self.pieces[*id].cells[i] = (x, y);
+                self.cells[y][x] = Some(Cell::Piece(*id));
+            }
+        }
+
+        true
+    }
+
+    // Robots take turns, so every instruction is carried out by the next robot in line
+    fn step(&mut self) {
+        let Some(instruction) = self.instructions.pop_front() else {
+            return;
+        };
+
+        let robot = self.robots[self.turn % self.robots.len()];
+        self.push(robot, instruction);
+        self.turn += 1;
+    }
+
+    fn run(&mut self) {
+        while !self.instructions.is_empty() {
+            self.step();
+        }
+    }
+
+    fn checksum(&self) -> usize {
+        self.pieces
+            .iter()
+            .filter(|piece| piece.shape != Shape::Robot)
+            .map(|piece| {
+                // Objects are measured from the top left corner of their bounding box
+                let x = piece.cells.iter().map(|(x, _)| *x).min().unwrap();
+                let y = piece.cells.iter().map(|(_, y)| *y).min().unwrap();
+
+                100 * y + x
+            })
+            .sum()
+    }
+}
+
+impl From<Game> for Warehouse {
+    fn from(game: Game) -> Self {
+        let mut warehouse = Warehouse::parse(&game.map.render(Some(game.robot)));
+        warehouse.instructions = game.instructions;
+        warehouse
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    Move(Move),
+    Next,
+    Undo,
+    Redo,
+    Quit,
+}
+
+impl Command {
+    // Parses keyboard input, which may contain WASD keys or the escape sequences sent by
+    // the arrow keys
+    fn parse_line(line: &str) -> Vec<Command> {
+        let mut result = vec![];
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let command = match c {
+                '\x1b' => {
+                    if chars.next() != Some('[') {
+                        continue;
+                    }
+
+                    match chars.next() {
+                        Some('A') => Command::Move(Move::Up),
+                        Some('B') => Command::Move(Move::Down),
+                        Some('C') => Command::Move(Move::Right),
+                        Some('D') => Command::Move(Move::Left),
+                        _ => continue,
+                    }
+                }
+                'w' | 'W' => Command::Move(Move::Up),
+                'a' | 'A' => Command::Move(Move::Left),
+                's' | 'S' => Command::Move(Move::Down),
+                'd' | 'D' => Command::Move(Move::Right),
+                'n' | 'N' | ' ' => Command::Next,
+                'u' | 'U' | 'z' | 'Z' => Command::Undo,
+                'r' | 'R' | 'y' | 'Y' => Command::Redo,
+                'q' | 'Q' => Command::Quit,
+                _ => continue,
+            };
+
+            result.push(command);
+        }
+
+        result
+    }
+}
+
+// A tile changed by a move, with its contents before and after it
+type TileChange = ((usize, usize), Option<Object>, Option<Object>);
+
+// Everything a move changed, so it can be undone and redone without keeping a copy of the
+// whole game around
+#[derive(Debug)]
+struct Delta {
+    tiles: Vec<TileChange>,
+    robot: ((usize, usize), (usize, usize)),
+    // The instruction taken from the queue, if the move was the next one of the input
+    instruction: Option<Move>,
+}
+
+struct Player {
+    game: Game,
+    undo: Vec<Delta>,
+    redo: Vec<Delta>,
+}
+
+impl Player {
+    fn new(game: Game) -> Self {
+        Self {
+            game,
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    // Applies a move and records the tiles it changed
+    fn record(&mut self, instruction: Move, from_queue: bool) {
+        let tiles = self.game.map.tiles.clone();
+        let robot = self.game.robot;
+
+        self.game.apply(instruction);
+
+        let mut changed = vec![];
+
+        for (y, (before, after)) in tiles.iter().zip(&self.game.map.tiles).enumerate() {
+            for (x, (before, after)) in before.iter().zip(after).enumerate() {
+                if before != after {
+                    changed.push(((x, y), *before, *after));
+                }
+            }
+        }
+
+        self.undo.push(Delta {
+            tiles: changed,
+            robot: (robot, self.game.robot),
+            instruction: from_queue.then_some(instruction),
+        });
+        self.redo.clear();
+    }
+
+    // Applies a command and returns whether the player wants to keep playing
+    fn apply(&mut self, command: Command) -> bool {
+        match command {
+            Command::Move(instruction) => self.record(instruction, false),
+            Command::Next => {
+                if let Some(instruction) = self.game.instructions.pop_front() {
+                    self.record(instruction, true);
+                }
+            }
+            Command::Undo => {
+                if let Some(delta) = self.undo.pop() {
+                    for ((x, y), before, _) in &delta.tiles {
+                        self.game.map.tiles[*y][*x] = *before;
+                    }
+
+                    self.game.robot = delta.robot.0;
+
+                    if let Some(instruction) = delta.instruction {
+                        self.game.instructions.push_front(instruction);
+                    }
+
+                    self.redo.push(delta);
+                }
+            }
+            Command::Redo => {
+                if let Some(delta) = self.redo.pop() {
+                    for ((x, y), _, after) in &delta.tiles {
+                        self.game.map.tiles[*y][*x] = *after;
+                    }
+
+                    self.game.robot = delta.robot.1;
+
+                    if delta.instruction.is_some() {
+                        self.game.instructions.pop_front();
+                    }
+
+                    self.undo.push(delta);
+                }
+            }
+            Command::Quit => return false,
+        }
+
+        true
+    }
+
+    fn render(&self) -> String {
+        let next = self
+            .game
+            .instructions
+            .front()
+            .map(|instruction| char::from(*instruction))
+            .unwrap_or('-');
+
+        format!(
+            "{}\nMoves left: {} (next: {}) | Checksum: {}\n\
+             WASD/arrows: move, n/space: next move, u: undo, r: redo, q: quit",
+            self.game.map.render(Some(self.game.robot)),
+            self.game.instructions.len(),
+            next,
+            self.game.checksum()
+        )
+    }
+}
+
+// Switches the terminal to unbuffered input without echo, so every key press arrives right
+// away instead of after Enter, and restores the previous settings when dropped. Without
+// `stty` or a terminal, input stays line-buffered.
+struct RawInput {
+    settings: Option<String>,
+}
+
+impl RawInput {
+    fn enable() -> Self {
+        let settings = process::Command::new("stty")
+            .arg("-g")
+            .stdin(process::Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+        if settings.is_some() {
+            let _ = process::Command::new("stty")
+                .args(["-icanon", "-echo", "min", "1"])
+                .stdin(process::Stdio::inherit())
+                .status();
+        }
+
+        Self { settings }
+    }
+}
+
+impl Drop for RawInput {
+    fn drop(&mut self) {
+        if let Some(settings) = &self.settings {
+            let _ = process::Command::new("stty")
+                .arg(settings)
+                .stdin(process::Stdio::inherit())
+                .status();
+        }
+    }
+}
+
+fn play(game: Game) {
+    let mut player = Player::new(game);
+
+    let _raw_input = RawInput::enable();
+    let mut stdin = io::stdin().lock();
+    let mut buffer = [0; 64];
+
+    println!("\x1b[2J\x1b[H{}", player.render());
+
+    loop {
+        let read = stdin.read(&mut buffer).expect("Failed to read input");
+
+        if read == 0 {
+            return;
+        }
+
+        // Arrow keys send escape sequences, which arrive together in a single read
+        for command in Command::parse_line(&String::from_utf8_lossy(&buffer[..read])) {
+            if !player.apply(command) {
+                return;
+            }
+        }
+
+        println!("\x1b[2J\x1b[H{}", player.render());
+    }
+}
+
 fn main() {
     let input = fs::read_to_string("./inputs/day15.txt").expect("Failed to read file");
 
-    let mut game = Game::parse(&input);
+    if env::args().any(|arg| arg == "--play") {
+        let game = Game::parse(&input);
+
+        if env::args().any(|arg| arg == "--wide") {
+            play(game.widen());
+        } else {
+            play(game);
+        }
+
+        return;
+    }
+
+    let mut warehouse = Warehouse::parse(&input);
 
-    game.run();
+    warehouse.run();
 
-    let result = game.checksum();
+    let result = warehouse.checksum();
 
     println!("Result (Part 1): {result}");
+
+    let mut warehouse = Warehouse::from(Game::parse(&input).widen());
+
+    warehouse.run();
+
+    let result = warehouse.checksum();
+
+    println!("Result (Part 2): {result}");
 }
 
 #[cfg(test)]
@@ -679,4 +1335,408 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
 
         assert_eq!(game.checksum(), 10092);
     }
+
+    #[test]
+    fn test_widen() {
+        let input = r#"
+#####
+#.O@#
+#####
+
+<
+        "#;
+
+        let game = Game::parse(input).widen();
+
+        let expected = r#"
+##########
+##..[]@.##
+##########
+        "#;
+
+        let (expected_map, expected_robot_position) = Map::parse(expected);
+
+        assert_eq!(game.robot, expected_robot_position);
+        assert_eq!(game.map, expected_map);
+    }
+
+    #[test]
+    fn test_push_vertical_blocked() {
+        let input = r#"
+######
+##...#
+#..#.#
+#.[].#
+#..[]#
+#..@.#
+######
+        "#;
+
+        let (mut map, _) = Map::parse(input);
+
+        let before = map.clone();
+
+        assert!(!map.try_shift_up((3, 4)));
+        assert_eq!(map, before);
+    }
+
+    #[test]
+    fn test_push_vertical_tree() {
+        let input = r#"
+#######
+#.....#
+#.[][]#
+#..[].#
+#..@..#
+#######
+        "#;
+
+        let (mut map, _) = Map::parse(input);
+
+        assert!(map.try_shift_up((3, 3)));
+
+        let expected = r#"
+#######
+#.[][]#
+#..[].#
+#.....#
+#..@..#
+#######
+        "#;
+
+        let (expected_map, _) = Map::parse(expected);
+
+        assert_eq!(map, expected_map);
+    }
+
+    #[test]
+    fn test_wide_example_one() {
+        let input = r#"
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^
+        "#;
+
+        let mut game = Game::parse(input).widen();
+
+        game.run();
+
+        let expected = r#"
+##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############
+        "#;
+
+        let (expected_map, expected_robot_position) = Map::parse(expected);
+
+        assert_eq!(game.robot, expected_robot_position);
+        assert_eq!(game.map, expected_map);
+    }
+
+    #[test]
+    fn test_wide_example_two() {
+        let input = r#"
+##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########
+
+<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
+vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
+><>vv>v^v^<>><>>>><^^>vv>v<^^^>>v^v^<^^>v^^>v^<^v>v<>>v^v^<v>v^^<^^vv<
+<<v<^>>^^^^>>>v^<>vvv^><v<<<>^^^vv^<vvv>^>v<^^^^v<>^>vvvv><>>v^<<^^^^^
+^><^><>>><>^^<<^^v>>><^<v>^<vv>>v>>>^v><>^v><<<<v>>v<v<v>vvv>^<><<>^><
+^>><>^v<><^vvv<^^<><v<<<<<><^v<<<><<<^^<v<^^^><^>>^<v^><<<^>>^v<v^v<v^
+>^>>^v>vv>^<<^v<>><<><<v<<v><>v<^vv<<<>^^v^>^^>>><<^v>>v^v><^^>>^<>vv^
+<><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
+^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
+v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
+        "#;
+
+        let mut game = Game::parse(input).widen();
+
+        game.run();
+
+        let expected = r#"
+####################
+##[].......[].[][]##
+##[]...........[].##
+##[]........[][][]##
+##[]......[]....[]##
+##..##......[]....##
+##..[]............##
+##..@......[].[][]##
+##......[][]..[]..##
+####################
+        "#;
+
+        let (expected_map, expected_robot_position) = Map::parse(expected);
+
+        assert_eq!(game.robot, expected_robot_position);
+        assert_eq!(game.map, expected_map);
+
+        assert_eq!(game.checksum(), 9021);
+    }
+
+    #[test]
+    fn test_command_parse_line() {
+        assert_eq!(
+            Command::parse_line("wD\x1b[A\x1b[Dnuq?"),
+            vec![
+                Command::Move(Move::Up),
+                Command::Move(Move::Right),
+                Command::Move(Move::Up),
+                Command::Move(Move::Left),
+                Command::Next,
+                Command::Undo,
+                Command::Quit,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_player_undo_redo() {
+        let input = r#"
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+<^^>>>vv<v>>v<<
+        "#;
+
+        let initial = Game::parse(input);
+
+        let mut player = Player::new(initial.clone());
+
+        assert!(player.apply(Command::Move(Move::Right)));
+        assert!(player.apply(Command::Move(Move::Right)));
+        assert_eq!(player.game.robot, (4, 2));
+        assert_eq!(player.game.map.tiles[2][5], Some(Object::Box));
+
+        // Only the tiles a move changes are kept for undoing it
+        assert!(player.undo[0].tiles.is_empty());
+        assert_eq!(player.undo[1].tiles.len(), 2);
+
+        player.apply(Command::Undo);
+        player.apply(Command::Undo);
+        player.apply(Command::Undo);
+        assert_eq!(player.game.robot, initial.robot);
+        assert_eq!(player.game.map, initial.map);
+
+        player.apply(Command::Redo);
+        assert_eq!(player.game.robot, (3, 2));
+
+        // Making a new move discards the redo history
+        player.apply(Command::Next);
+        player.apply(Command::Redo);
+        assert_eq!(player.game.robot, (2, 2));
+        assert_eq!(
+            player.game.instructions.len(),
+            initial.instructions.len() - 1
+        );
+
+        player.apply(Command::Undo);
+        assert_eq!(player.game.instructions.len(), initial.instructions.len());
+
+        assert!(!player.apply(Command::Quit));
+    }
+
+    #[test]
+    fn test_player_step_through() {
+        let input = r#"
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+<^^>>>vv<v>>v<<
+        "#;
+
+        let mut game = Game::parse(input);
+        let mut player = Player::new(game.clone());
+
+        while !player.game.instructions.is_empty() {
+            player.apply(Command::Next);
+        }
+
+        game.run();
+
+        assert_eq!(player.game.robot, game.robot);
+        assert_eq!(player.game.map, game.map);
+        assert_eq!(player.undo.len(), 15);
+    }
+
+    #[test]
+    fn test_warehouse_matches_game() {
+        let input = r#"
+##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########
+
+<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
+vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
+><>vv>v^v^<>><>>>><^^>vv>v<^^^>>v^v^<^^>v^^>v^<^v>v<>>v^v^<v>v^^<^^vv<
+<<v<^>>^^^^>>>v^<>vvv^><v<<<>^^^vv^<vvv>^>v<^^^^v<>^>vvvv><>>v^<<^^^^^
+^><^><>>><>^^<<^^v>>><^<v>^<vv>>v>>>^v><>^v><<<<v>>v<v<v>vvv>^<><<>^><
+^>><>^v<><^vvv<^^<><v<<<<<><^v<<<><<<^^<v<^^^><^>>^<v^><<<^>>^v<v^v<v^
+>^>>^v>vv>^<<^v<>><<><<v<<v><>v<^vv<<<>^^v^>^^>>><<^v>>v^v><^^>>^<>vv^
+<><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
+^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
+v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
+        "#;
+
+        let mut warehouse = Warehouse::parse(input);
+        let mut game = Game::parse(input);
+
+        warehouse.run();
+        game.run();
+
+        assert_eq!(warehouse.render(), game.map.render(Some(game.robot)));
+        assert_eq!(warehouse.checksum(), 10092);
+
+        let mut warehouse = Warehouse::from(Game::parse(input).widen());
+        let mut game = Game::parse(input).widen();
+
+        warehouse.run();
+        game.run();
+
+        assert_eq!(warehouse.render(), game.map.render(Some(game.robot)));
+        assert_eq!(warehouse.checksum(), 9021);
+    }
+
+    #[test]
+    fn test_warehouse_polyomino() {
+        let input = r#"
+#######
+#.....#
+#.O...#
+#.aa..#
+#..a..#
+#..@..#
+#######
+
+^>^<
+        "#;
+
+        let mut warehouse = Warehouse::parse(input);
+
+        assert_eq!(warehouse.pieces.len(), 3);
+        assert_eq!(
+            warehouse.pieces[1],
+            Piece {
+                shape: Shape::Piece('a'),
+                cells: vec![(2, 3), (3, 3), (3, 4)],
+            }
+        );
+
+        warehouse.run();
+
+        let expected = r#"
+#######
+#.O...#
+#aa...#
+#.a@..#
+#.....#
+#.....#
+#######
+        "#;
+
+        assert_eq!(warehouse.render().trim(), expected.trim());
+        assert_eq!(warehouse.checksum(), 102 + 201);
+    }
+
+    #[test]
+    fn test_warehouse_push_graph() {
+        let input = r#"
+#######
+#.....#
+#.bO..#
+#.aa..#
+#..a@.#
+#######
+        "#;
+
+        let mut warehouse = Warehouse::parse(input);
+
+        let graph = warehouse.resolve_push(3, Move::Left).unwrap();
+        assert_eq!(graph, PushGraph::from([(3, vec![2]), (2, vec![])]));
+
+        let graph = warehouse.resolve_push(2, Move::Up).unwrap();
+        assert_eq!(
+            graph,
+            PushGraph::from([(2, vec![0, 1]), (0, vec![]), (1, vec![])])
+        );
+
+        // Pushing the polyomino up twice runs the box into the wall
+        assert!(warehouse.push(2, Move::Up));
+        let before = warehouse.render();
+        assert!(!warehouse.push(2, Move::Up));
+        assert_eq!(warehouse.render(), before);
+    }
+
+    #[test]
+    fn test_warehouse_robots_take_turns() {
+        let input = r#"
+########
+#@.O.@.#
+#......#
+########
+
+><><v
+        "#;
+
+        let mut warehouse = Warehouse::parse(input);
+
+        assert_eq!(warehouse.robots, vec![0, 2]);
+
+        warehouse.run();
+
+        // The robots end up pushing the box and each other back and forth
+        let expected = r#"
+########
+#..O@..#
+#.@....#
+########
+        "#;
+
+        assert_eq!(warehouse.render().trim(), expected.trim());
+    }
+
+    #[test]
+    #[should_panic(expected = "Warehouse without robots")]
+    fn test_warehouse_without_robots() {
+        Warehouse::parse("####\n#O.#\n####\n\n<");
+    }
 }
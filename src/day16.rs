@@ -1,5 +1,11 @@
-use std::{collections::HashSet, fs};
-
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    fs,
+};
+
+use itertools::Itertools;
+use num_bigint::BigUint;
 use pathfinding::prelude::astar_bag_collect;
 
 #[derive(Debug)]
@@ -85,7 +91,7 @@ impl Map {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
 enum Direction {
     North,
     South,
@@ -111,26 +117,128 @@ impl Direction {
             Direction::South => Direction::East,
         }
     }
+
+    fn reverse(self) -> Self {
+        self.rotate_clockwise().rotate_clockwise()
+    }
+}
+
+type State = (usize, usize, Direction);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CostModel {
+    step: usize,
+    turn_clockwise: usize,
+    turn_counterclockwise: usize,
+    u_turn: Option<usize>,
+    start_directions: Vec<Direction>,
+    end_direction: Option<Direction>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            step: 1,
+            turn_clockwise: 1000,
+            turn_counterclockwise: 1000,
+            u_turn: None,
+            start_directions: vec![Direction::East],
+            end_direction: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BestPaths {
+    cost: usize,
+    tiles: HashSet<(usize, usize)>,
+    count: BigUint,
 }
 
 struct Maze {
     map: Map,
-    reindeer: (usize, usize, Direction),
+    costs: CostModel,
 }
 
 impl Maze {
     fn parse(input: &str) -> Self {
+        Self::parse_with_costs(input, CostModel::default())
+    }
+
+    fn parse_with_costs(input: &str, costs: CostModel) -> Self {
+        // Path counting walks optimal states in cost order, which needs every move to cost something
+        assert!(
+            costs.step > 0
+                && costs.turn_clockwise > 0
+                && costs.turn_counterclockwise > 0
+                && costs.u_turn != Some(0),
+            "Movement and turn costs must be positive"
+        );
+
         let map = Map::parse(input);
 
-        let reindeer = (map.start.0, map.start.1, Direction::East);
+        Self { map, costs }
+    }
+
+    fn start_states(&self) -> Vec<State> {
+        let (x, y) = self.map.start;
 
-        Self { map, reindeer }
+        self.costs
+            .start_directions
+            .iter()
+            .map(|direction| (x, y, *direction))
+            .collect()
+    }
+
+    fn end_states(&self) -> Vec<State> {
+        let (x, y) = self.map.end;
+
+        [
+            Direction::North,
+            Direction::South,
+            Direction::West,
+            Direction::East,
+        ]
+        .into_iter()
+        .filter(|direction| self.costs.end_direction.is_none_or(|d| d == *direction))
+        .map(|direction| (x, y, direction))
+        .collect()
     }
 
-    fn next_positions(
+    fn is_end_state(&self, (x, y, direction): State) -> bool {
+        (x, y) == self.map.end && self.costs.end_direction.is_none_or(|d| d == direction)
+    }
+
+    fn rotations(
         &self,
-        current_position: (usize, usize, Direction),
+        (x, y, direction): State,
+        reversed: bool,
     ) -> Vec<(usize, usize, Direction, usize)> {
+        let (clockwise, counterclockwise) = if reversed {
+            (
+                direction.rotate_counterclockwise(),
+                direction.rotate_clockwise(),
+            )
+        } else {
+            (
+                direction.rotate_clockwise(),
+                direction.rotate_counterclockwise(),
+            )
+        };
+
+        let mut result = vec![
+            (x, y, clockwise, self.costs.turn_clockwise),
+            (x, y, counterclockwise, self.costs.turn_counterclockwise),
+        ];
+
+        if let Some(u_turn) = self.costs.u_turn {
+            result.push((x, y, direction.reverse(), u_turn));
+        }
+
+        result
+    }
+
+    fn next_positions(&self, current_position: State) -> Vec<(usize, usize, Direction, usize)> {
         let forward_pos = match current_position.2 {
             Direction::North => (current_position.0 as isize, current_position.1 as isize - 1),
             Direction::South => (current_position.0 as isize, current_position.1 as isize + 1),
@@ -138,20 +246,7 @@ impl Maze {
             Direction::East => (current_position.0 as isize + 1, current_position.1 as isize),
         };
 
-        let rotated_current_position = vec![
-            (
-                current_position.0,
-                current_position.1,
-                current_position.2.rotate_clockwise(),
-                1000,
-            ),
-            (
-                current_position.0,
-                current_position.1,
-                current_position.2.rotate_counterclockwise(),
-                1000,
-            ),
-        ];
+        let rotated_current_position = self.rotations(current_position, false);
 
         if forward_pos.0 < 0
             || forward_pos.1 < 0
@@ -167,7 +262,12 @@ impl Maze {
 
         match forward_tile {
             Tile::Empty => {
-                let mut result = vec![(forward_pos.0, forward_pos.1, current_position.2, 1)];
+                let mut result = vec![(
+                    forward_pos.0,
+                    forward_pos.1,
+                    current_position.2,
+                    self.costs.step,
+                )];
                 result.append(&mut rotated_current_position.clone());
                 result
             }
@@ -175,11 +275,171 @@ impl Maze {
         }
     }
 
-    fn find_shortest_paths(&self) -> Option<(Vec<Vec<(usize, usize, Direction)>>, usize)> {
+    fn previous_positions(&self, current_position: State) -> Vec<(usize, usize, Direction, usize)> {
+        let (x, y, direction) = current_position;
+
+        let mut result = self.rotations(current_position, true);
+
+        let backward_pos = match direction {
+            Direction::North => (x as isize, y as isize + 1),
+            Direction::South => (x as isize, y as isize - 1),
+            Direction::West => (x as isize + 1, y as isize),
+            Direction::East => (x as isize - 1, y as isize),
+        };
+
+        if backward_pos.0 < 0
+            || backward_pos.1 < 0
+            || backward_pos.0 >= self.map.width() as isize
+            || backward_pos.1 >= self.map.height() as isize
+        {
+            return result;
+        }
+
+        let (bx, by) = (backward_pos.0 as usize, backward_pos.1 as usize);
+
+        if let Tile::Empty = self.map.tiles[by][bx] {
+            result.push((bx, by, direction, self.costs.step));
+        }
+
+        result
+    }
+
+    fn state_index(&self, (x, y, direction): State) -> usize {
+        let direction = match direction {
+            Direction::North => 0,
+            Direction::South => 1,
+            Direction::West => 2,
+            Direction::East => 3,
+        };
+
+        (y * self.map.width() + x) * 4 + direction
+    }
+
+    fn dijkstra(
+        &self,
+        starts: &[State],
+        successors: impl Fn(State) -> Vec<(usize, usize, Direction, usize)>,
+    ) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.map.width() * self.map.height() * 4];
+        let mut queue = BinaryHeap::new();
+
+        for start in starts {
+            distances[self.state_index(*start)] = Some(0);
+            queue.push(Reverse((0, *start)));
+        }
+
+        while let Some(Reverse((cost, position))) = queue.pop() {
+            if distances[self.state_index(position)].is_some_and(|d| d < cost) {
+                continue;
+            }
+
+            for (x, y, direction, step_cost) in successors(position) {
+                let next = (x, y, direction);
+                let next_cost = cost + step_cost;
+                let index = self.state_index(next);
+
+                if distances[index].is_none_or(|d| next_cost < d) {
+                    distances[index] = Some(next_cost);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+
+        distances
+    }
+
+    // Runs Dijkstra from the start and backwards from the end to find every state on an
+    // optimal path, then counts the optimal paths over the resulting predecessor DAG.
+    // Counting assumes all costs are positive, otherwise there can be infinitely many paths.
+    fn find_best_paths(&self) -> Option<BestPaths> {
+        let start_states = self.start_states();
+        let end_states = self.end_states();
+
+        let from_start = self.dijkstra(&start_states, |p| self.next_positions(p));
+        let to_end = self.dijkstra(&end_states, |p| self.previous_positions(p));
+
+        let cost = end_states
+            .iter()
+            .filter_map(|state| from_start[self.state_index(*state)])
+            .min()?;
+
+        let mut optimal_states = vec![];
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                for direction in [
+                    Direction::North,
+                    Direction::South,
+                    Direction::West,
+                    Direction::East,
+                ] {
+                    let index = self.state_index((x, y, direction));
+
+                    if let (Some(a), Some(b)) = (from_start[index], to_end[index]) {
+                        if a + b == cost {
+                            optimal_states.push((a, (x, y, direction)));
+                        }
+                    }
+                }
+            }
+        }
+
+        optimal_states.sort();
+
+        let tiles = optimal_states
+            .iter()
+            .map(|(_, (x, y, _))| (*x, *y))
+            .collect();
+
+        let mut counts = vec![BigUint::ZERO; from_start.len()];
+        for state in &start_states {
+            counts[self.state_index(*state)] = BigUint::from(1u8);
+        }
+
+        for (distance, state) in &optimal_states {
+            if self.is_end_state(*state) {
+                continue;
+            }
+
+            let current = counts[self.state_index(*state)].clone();
+
+            for (x, y, direction, step_cost) in self.next_positions(*state) {
+                let index = self.state_index((x, y, direction));
+
+                if from_start[index] == Some(distance + step_cost)
+                    && to_end[index].map(|d| distance + step_cost + d) == Some(cost)
+                {
+                    counts[index] += &current;
+                }
+            }
+        }
+
+        let count = end_states
+            .iter()
+            .filter(|state| from_start[self.state_index(**state)] == Some(cost))
+            .map(|state| counts[self.state_index(*state)].clone())
+            .sum();
+
+        Some(BestPaths { cost, tiles, count })
+    }
+
+    fn find_shortest_paths(&self) -> Option<(Vec<Vec<State>>, usize)> {
+        self.start_states()
+            .into_iter()
+            .filter_map(|start| self.find_shortest_paths_from(start))
+            .min_set_by_key(|(_, cost)| *cost)
+            .into_iter()
+            .reduce(|(mut paths, cost), (mut other, _)| {
+                paths.append(&mut other);
+                (paths, cost)
+            })
+    }
+
+    fn find_shortest_paths_from(&self, start: State) -> Option<(Vec<Vec<State>>, usize)> {
         let mut visited = HashSet::new();
 
         let result = astar_bag_collect(
-            &self.reindeer,
+            &start,
             |&(x, y, direction)| {
                 let successors = self
                     .next_positions((x, y, direction))
@@ -196,8 +456,9 @@ impl Maze {
 
                 (((ex as isize - x as isize).pow(2) + (ey as isize - y as isize).pow(2)) as f64)
                     .sqrt() as usize
+                    * self.costs.step
             },
-            |(x, y, _)| (*x, *y) == self.map.end,
+            |state| self.is_end_state(*state),
         );
 
         result
@@ -209,23 +470,15 @@ fn main() {
 
     let maze = Maze::parse(&input);
 
-    let (shortest_paths, cost) = maze.find_shortest_paths().unwrap();
+    let (_, cost) = maze.find_shortest_paths().unwrap();
 
     println!("Result (Part 1): {cost}");
 
-    let common_tiles = shortest_paths
-        .into_iter()
-        .map(|tiles| {
-            tiles
-                .iter()
-                .map(|tile| (tile.0, tile.1))
-                .collect::<HashSet<(usize, usize)>>()
-        })
-        .reduce(|acc, e| acc.union(&e).copied().collect())
-        .unwrap()
-        .len();
-
-    println!("Result (Part 2): {common_tiles}");
+    let best_paths = maze.find_best_paths().unwrap();
+
+    println!("Result (Part 2): {}", best_paths.tiles.len());
+
+    println!("Number of optimal paths: {}", best_paths.count);
 }
 
 #[cfg(test)]
@@ -267,4 +520,184 @@ mod tests {
 
         assert_eq!(cost, 7036);
     }
+
+    #[test]
+    fn test_find_best_paths() {
+        let input = r#"
+###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############
+        "#;
+
+        let maze = Maze::parse(input);
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        let (shortest_paths, _) = maze.find_shortest_paths().unwrap();
+
+        assert_eq!(best_paths.cost, 7036);
+        assert_eq!(best_paths.tiles.len(), 45);
+        assert_eq!(best_paths.count, BigUint::from(shortest_paths.len()));
+    }
+
+    #[test]
+    fn test_find_best_paths_second_example() {
+        let input = r#"
+#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################
+        "#;
+
+        let maze = Maze::parse(input);
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        let (shortest_paths, _) = maze.find_shortest_paths().unwrap();
+
+        assert_eq!(best_paths.cost, 11048);
+        assert_eq!(best_paths.tiles.len(), 64);
+        assert_eq!(best_paths.count, BigUint::from(shortest_paths.len()));
+    }
+
+    #[test]
+    fn test_find_best_paths_pillar() {
+        let input = r#"
+#######
+#.....#
+#S#.#E#
+#.....#
+#######
+        "#;
+
+        let maze = Maze::parse(input);
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        assert_eq!(best_paths.cost, 3006);
+        assert_eq!(best_paths.tiles.len(), 12);
+        assert_eq!(best_paths.count, BigUint::from(2u8));
+    }
+
+    #[test]
+    fn test_cost_model() {
+        let input = r#"
+#######
+#.....#
+#S#.#E#
+#.....#
+#######
+        "#;
+
+        let maze = Maze::parse_with_costs(
+            input,
+            CostModel {
+                end_direction: Some(Direction::North),
+                ..CostModel::default()
+            },
+        );
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        assert_eq!(best_paths.cost, 3006);
+        assert_eq!(best_paths.tiles.len(), 7);
+        assert_eq!(best_paths.count, BigUint::from(1u8));
+
+        let maze = Maze::parse_with_costs(
+            input,
+            CostModel {
+                start_directions: vec![
+                    Direction::North,
+                    Direction::South,
+                    Direction::West,
+                    Direction::East,
+                ],
+                ..CostModel::default()
+            },
+        );
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        assert_eq!(best_paths.cost, 2006);
+        assert_eq!(best_paths.count, BigUint::from(2u8));
+        assert_eq!(maze.find_shortest_paths().unwrap().0.len(), 2);
+
+        let maze = Maze::parse_with_costs(
+            input,
+            CostModel {
+                step: 10,
+                turn_clockwise: 1,
+                turn_counterclockwise: 100,
+                ..CostModel::default()
+            },
+        );
+
+        let best_paths = maze.find_best_paths().unwrap();
+
+        // Three clockwise turns are cheaper than a single counterclockwise one
+        assert_eq!(best_paths.cost, 3 + 10 + 1 + 40 + 1 + 10);
+        assert_eq!(best_paths.count, BigUint::from(1u8));
+        assert_eq!(maze.find_shortest_paths().unwrap().1, best_paths.cost);
+    }
+
+    #[test]
+    fn test_cost_model_u_turn() {
+        let input = r#"
+#######
+#E...S#
+#######
+        "#;
+
+        let maze = Maze::parse(input);
+
+        assert_eq!(maze.find_best_paths().unwrap().cost, 2004);
+
+        let maze = Maze::parse_with_costs(
+            input,
+            CostModel {
+                u_turn: Some(1500),
+                ..CostModel::default()
+            },
+        );
+
+        assert_eq!(maze.find_best_paths().unwrap().cost, 1504);
+        assert_eq!(maze.find_shortest_paths().unwrap().1, 1504);
+    }
+
+    #[test]
+    #[should_panic(expected = "Movement and turn costs must be positive")]
+    fn test_cost_model_rejects_free_turns() {
+        Maze::parse_with_costs(
+            "#####\n#S.E#\n#####",
+            CostModel {
+                turn_clockwise: 0,
+                ..CostModel::default()
+            },
+        );
+    }
 }
@@ -1,7 +1,9 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+};
 
-use pathfinding::prelude::astar_bag_collect;
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
@@ -64,84 +66,219 @@ impl Map {
         Map { tiles, start, end }
     }
 
-    fn find_shortest_paths(&self) -> Option<(Vec<Vec<(usize, usize)>>, usize)> {
-        let start = self.start;
-        let end = self.end;
-        astar_bag_collect(
-            &start,
-            |&(x, y)| {
-                [
-                    (x as isize, y as isize - 1),
-                    (x as isize, y as isize + 1),
-                    (x as isize - 1, y as isize),
-                    (x as isize + 1, y as isize),
-                ]
+    fn neighbours(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        [
+            (x as isize, y as isize - 1),
+            (x as isize, y as isize + 1),
+            (x as isize - 1, y as isize),
+            (x as isize + 1, y as isize),
+        ]
+        .into_iter()
+        .filter_map(|(x, y)| -> Option<(usize, usize)> {
+            let is_within_bounds =
+                x >= 0 && y >= 0 && (x as usize) < self.width() && (y as usize) < self.height();
+
+            if !is_within_bounds {
+                return None;
+            }
+
+            let x = x as usize;
+            let y = y as usize;
+
+            if let Tile::Wall = self.tiles[y][x] {
+                return None;
+            }
+
+            Some((x, y))
+        })
+        .collect()
+    }
+
+    // Returns the length of the shortest path from the origin to every reachable tile
+    fn distances_from(&self, origin: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+        let mut distances = vec![vec![None; self.width()]; self.height()];
+        let mut queue = VecDeque::new();
+
+        distances[origin.1][origin.0] = Some(0);
+        queue.push_back(origin);
+
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[position.1][position.0].unwrap();
+
+            for (x, y) in self.neighbours(position) {
+                if distances[y][x].is_none() {
+                    distances[y][x] = Some(distance + 1);
+                    queue.push_back((x, y));
+                }
+            }
+        }
+
+        distances
+    }
+
+    // Follows the distance field downhill from the given position back to its origin
+    fn route_from(
+        &self,
+        distances: &[Vec<Option<usize>>],
+        position: (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let mut route = vec![position];
+        let mut current = position;
+
+        while let Some(distance) = distances[current.1][current.0] {
+            if distance == 0 {
+                break;
+            }
+
+            current = self
+                .neighbours(current)
                 .into_iter()
-                .filter_map(|(x, y)| -> Option<(usize, usize)> {
-                    let is_within_bounds = x >= 0
-                        && y >= 0
-                        && (x as usize) < self.width()
-                        && (y as usize) < self.height();
-
-                    if !is_within_bounds {
-                        return None;
-                    }
+                .find(|&(x, y)| distances[y][x] == Some(distance - 1))
+                .unwrap();
 
-                    let x = x as usize;
-                    let y = y as usize;
+            route.push(current);
+        }
 
-                    if let Tile::Wall = self.tiles[y][x] {
-                        return None;
-                    }
+        route
+    }
 
-                    Some((x, y))
-                })
-                .map(|(x, y)| ((x, y), 1))
-                .collect::<Vec<_>>()
-            },
-            |&(x, y)| {
-                let (ex, ey) = end;
-
-                (((ex as isize - x as isize).pow(2) + (ey as isize - y as isize).pow(2)) as f64)
-                    .sqrt() as usize
-            },
-            |position| position == &end,
-        )
+    // Renders the map with the route taken when using the given cheat marked with 'O',
+    // the tiles passed while cheating with '*' and the cheat's start and end with '1' and '2'
+    fn render_cheat(&self, cheat: &Cheat) -> String {
+        let from_start = self.distances_from(self.start);
+        let to_end = self.distances_from(self.end);
+
+        let mut canvas: Vec<Vec<char>> = self
+            .tiles
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| match tile {
+                        Tile::Empty => '.',
+                        Tile::Wall => '#',
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for (x, y) in self
+            .route_from(&from_start, cheat.start)
+            .into_iter()
+            .chain(self.route_from(&to_end, cheat.end))
+        {
+            canvas[y][x] = 'O';
+        }
+
+        let (mut x, mut y) = cheat.start;
+        while (x, y) != cheat.end {
+            if x != cheat.end.0 {
+                x = if x < cheat.end.0 { x + 1 } else { x - 1 };
+            } else {
+                y = if y < cheat.end.1 { y + 1 } else { y - 1 };
+            }
+            canvas[y][x] = '*';
+        }
+
+        canvas[cheat.start.1][cheat.start.0] = '1';
+        canvas[cheat.end.1][cheat.end.0] = '2';
+        canvas[self.start.1][self.start.0] = 'S';
+        canvas[self.end.1][self.end.0] = 'E';
+
+        canvas
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 }
 
 fn find_cheats(map: &Map) -> (usize, HashMap<usize, usize>) {
-    let baseline = map.find_shortest_paths();
+    find_cheats_within(map, 2, 1)
+}
 
-    let baseline = baseline.unwrap().1;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Cheat {
+    saved: usize,
+    length: usize,
+    start: (usize, usize),
+    end: (usize, usize),
+}
 
-    let results: Vec<(usize, usize)> = (0..map.height())
+// Lists every cheat of at most `max_length` picoseconds that saves at least `min_saved`
+// picoseconds, together with the length of the regular route
+fn list_cheats(map: &Map, max_length: usize, min_saved: usize) -> (usize, Vec<Cheat>) {
+    let from_start = map.distances_from(map.start);
+    let to_end = map.distances_from(map.end);
+
+    let baseline = from_start[map.end.1][map.end.0].expect("End is not reachable");
+
+    let track: Vec<(usize, usize)> = (0..map.height())
         .flat_map(|y| (0..map.width()).map(move |x| (x, y)))
-        .filter(|&(x, y)| map.tiles[y][x] == Tile::Wall)
-        .par_bridge()
-        .filter_map(|(x, y)| {
-            let mut derived = map.clone();
-            derived.tiles[y][x] = Tile::Empty;
+        .filter(|&(x, y)| from_start[y][x].is_some())
+        .collect();
 
-            let paths = derived.find_shortest_paths();
+    let cheats: Vec<Cheat> = track
+        .par_iter()
+        .flat_map_iter(|&(x, y)| {
+            let start_distance = from_start[y][x].unwrap();
+            let max_length = max_length as isize;
 
-            let (paths, cost) = paths?;
+            let mut cheats = vec![];
 
-            let saved_cost = baseline.abs_diff(cost);
+            for dy in -max_length..=max_length {
+                let remaining = max_length - dy.abs();
 
-            if saved_cost < 1 {
-                return None;
+                for dx in -remaining..=remaining {
+                    if (dx, dy) == (0, 0) {
+                        continue;
+                    }
+
+                    let (ex, ey) = (x as isize + dx, y as isize + dy);
+
+                    if ex < 0 || ey < 0 || ex as usize >= map.width() || ey as usize >= map.height()
+                    {
+                        continue;
+                    }
+
+                    let Some(end_distance) = to_end[ey as usize][ex as usize] else {
+                        continue;
+                    };
+
+                    let length = (dx.abs() + dy.abs()) as usize;
+                    let cost = start_distance + length + end_distance;
+
+                    if cost + min_saved <= baseline {
+                        cheats.push(Cheat {
+                            saved: baseline - cost,
+                            length,
+                            start: (x, y),
+                            end: (ex as usize, ey as usize),
+                        });
+                    }
+                }
             }
 
-            Some((saved_cost, paths.len()))
+            cheats
         })
         .collect();
 
+    (baseline, cheats)
+}
+
+// Counts every cheat of at most `max_length` picoseconds that saves at least `min_saved`
+// picoseconds, grouped by the time saved
+fn find_cheats_within(
+    map: &Map,
+    max_length: usize,
+    min_saved: usize,
+) -> (usize, HashMap<usize, usize>) {
+    let (baseline, cheats) = list_cheats(map, max_length, min_saved);
+
     let mut result = HashMap::new();
 
-    for (saved_cost, count) in results {
-        let entry = result.entry(saved_cost).or_default();
-        *entry += count;
+    for cheat in cheats {
+        let entry = result.entry(cheat.saved).or_default();
+        *entry += 1;
     }
 
     (baseline, result)
@@ -166,6 +303,24 @@ fn main() {
         .sum();
 
     println!("Result (Part 1): {result}");
+
+    let (_, cheats) = find_cheats_within(&map, 20, 100);
+
+    let result: usize = cheats.values().sum();
+
+    println!("Result (Part 2): {result}");
+
+    let (_, mut cheats) = list_cheats(&map, 20, 100);
+
+    cheats.sort();
+
+    if let Some(best) = cheats.last() {
+        println!(
+            "Best cheat saves {} picoseconds ({:?} -> {:?}):",
+            best.saved, best.start, best.end
+        );
+        println!("{}", map.render_cheat(best));
+    }
 }
 
 #[cfg(test)]
@@ -217,4 +372,153 @@ mod tests {
             .collect::<HashMap<usize, usize>>()
         )
     }
+
+    #[test]
+    fn test_example_long_cheats() {
+        let input = r#"
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+        "#;
+
+        let map = Map::parse(input);
+
+        let (baseline, cheats) = find_cheats_within(&map, 20, 50);
+
+        assert_eq!(baseline, 84);
+
+        assert_eq!(
+            cheats,
+            [
+                (50, 32),
+                (52, 31),
+                (54, 29),
+                (56, 39),
+                (58, 25),
+                (60, 23),
+                (62, 20),
+                (64, 19),
+                (66, 12),
+                (68, 14),
+                (70, 12),
+                (72, 22),
+                (74, 4),
+                (76, 3)
+            ]
+            .into_iter()
+            .collect::<HashMap<usize, usize>>()
+        )
+    }
+
+    #[test]
+    fn test_list_cheats() {
+        let input = r#"
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+        "#;
+
+        let map = Map::parse(input);
+
+        let (_, cheats) = list_cheats(&map, 2, 64);
+
+        assert_eq!(
+            cheats,
+            vec![Cheat {
+                saved: 64,
+                length: 2,
+                start: (7, 7),
+                end: (5, 7)
+            }]
+        );
+
+        let (_, mut cheats) = list_cheats(&map, 20, 74);
+
+        cheats.sort();
+
+        assert_eq!(cheats.len(), 7);
+        assert_eq!(cheats.iter().filter(|cheat| cheat.saved == 76).count(), 3);
+        assert_eq!(
+            cheats.last(),
+            Some(&Cheat {
+                saved: 76,
+                length: 8,
+                start: (1, 3),
+                end: (5, 7)
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_cheat() {
+        let input = r#"
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+        "#;
+
+        let map = Map::parse(input);
+
+        let cheat = Cheat {
+            saved: 12,
+            length: 2,
+            start: (7, 1),
+            end: (9, 1),
+        };
+
+        assert_eq!(
+            map.render_cheat(&cheat),
+            r#"###############
+#OOO#OO1*2OOOO#
+#O#O#O#.#.###O#
+#S#OOO#.#.#OOO#
+#######.#.#O###
+#######.#.#OOO#
+#######.#.###O#
+###OOE#...#OOO#
+###O#######O###
+#OOO###OOO#OOO#
+#O#####O#O###O#
+#O#OOO#O#O#OOO#
+#O#O#O#O#O#O###
+#OOO#OOO#OOO###
+###############"#
+        );
+    }
 }
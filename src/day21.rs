@@ -4,9 +4,10 @@ use std::{
 };
 
 use itertools::Itertools;
+#[cfg(test)]
 use memoize::memoize;
 use pathfinding::prelude::astar_bag_collect;
-use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum DirectionalCommand {
@@ -29,6 +30,21 @@ impl From<DirectionalCommand> for char {
     }
 }
 
+impl TryFrom<char> for DirectionalCommand {
+    type Error = ();
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        Ok(match value {
+            '>' => DirectionalCommand::Right,
+            '<' => DirectionalCommand::Left,
+            '^' => DirectionalCommand::Up,
+            'v' => DirectionalCommand::Down,
+            'A' => DirectionalCommand::Activate,
+            _ => return Err(()),
+        })
+    }
+}
+
 impl TryFrom<char> for Key {
     type Error = ();
 
@@ -103,22 +119,6 @@ enum Key {
     Activate,
 }
 
-fn key_to_pos(key: Key) -> (usize, usize) {
-    match key {
-        Key::Seven => (0, 0),
-        Key::Four => (0, 1),
-        Key::One => (0, 2),
-        Key::Eight => (1, 0),
-        Key::Five => (1, 1),
-        Key::Two => (1, 2),
-        Key::Zero => (1, 3),
-        Key::Nine => (2, 0),
-        Key::Six => (2, 1),
-        Key::Three => (2, 2),
-        Key::Activate => (2, 3),
-    }
-}
-
 fn get_directional_command_from_delta(d: (isize, isize)) -> Option<DirectionalCommand> {
     match d {
         (0, 0) => None,
@@ -130,228 +130,188 @@ fn get_directional_command_from_delta(d: (isize, isize)) -> Option<DirectionalCo
     }
 }
 
-#[memoize]
-fn compute_shortest_paths_between_keys() -> HashMap<(Key, Key), HashSet<Vec<DirectionalCommand>>> {
-    let all_keys = [
-        Key::Zero,
-        Key::One,
-        Key::Two,
-        Key::Three,
-        Key::Four,
-        Key::Five,
-        Key::Six,
-        Key::Seven,
-        Key::Eight,
-        Key::Nine,
-        Key::Activate,
-    ];
-
-    let mut result = HashMap::new();
-
-    for a in all_keys {
-        for b in all_keys {
-            if a == b {
-                result.insert((a, b), HashSet::new());
-                continue;
-            }
+const NUMERIC_KEYPAD: &str = "
+789
+456
+123
+.0A
+";
 
-            let start_pos = key_to_pos(a);
-            let end_pos = key_to_pos(b);
-
-            let Some((shortest_paths, _)) = astar_bag_collect(
-                &start_pos,
-                |&(x, y)| {
-                    [
-                        (x as isize, y as isize - 1),
-                        (x as isize, y as isize + 1),
-                        (x as isize - 1, y as isize),
-                        (x as isize + 1, y as isize),
-                    ]
-                    .into_iter()
-                    .filter_map(|(x, y)| -> Option<(usize, usize)> {
-                        let is_within_bounds =
-                            x >= 0 && y >= 0 && (x as usize) < 3 && (y as usize) < 4;
+const DIRECTIONAL_KEYPAD: &str = "
+.^A
+<v>
+";
 
-                        if !is_within_bounds {
-                            return None;
-                        }
+type ShortestPaths<K> = HashMap<(K, K), HashSet<Vec<DirectionalCommand>>>;
 
-                        let x = x as usize;
-                        let y = y as usize;
+// A keypad layout, where '.' marks a gap the robot arm must never point at
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Keypad {
+    keys: HashMap<(usize, usize), char>,
+}
 
-                        if (x, y) == (0, 3) {
-                            // This position is forbidden
-                            return None;
-                        }
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KeypadError {
+    MissingActivate,
+    DuplicateKey(char),
+}
 
-                        Some((x, y))
-                    })
-                    .map(|(x, y)| ((x, y), 1))
-                    .collect::<Vec<_>>()
-                },
-                |&(x, y)| {
-                    let (ex, ey) = end_pos;
-                    (((ex as isize - x as isize).pow(2) + (ey as isize - y as isize).pow(2)) as f64)
-                        .sqrt() as usize
-                },
-                |position| position == &end_pos,
-            ) else {
-                panic!("Could not compute shortest paths");
-            };
+impl Keypad {
+    // Every arm starts out pointing at 'A', so a layout without it cannot be used
+    fn parse(layout: &str) -> Result<Self, KeypadError> {
+        let mut keys = HashMap::new();
 
-            let shortest_paths = shortest_paths
-                .into_iter()
-                .map(|positions| {
-                    positions
-                        .iter()
-                        .skip(1)
-                        .enumerate()
-                        .filter_map(|(i, pos)| {
-                            let last = positions[i];
-                            let delta = (
-                                pos.0 as isize - last.0 as isize,
-                                pos.1 as isize - last.1 as isize,
-                            );
-                            get_directional_command_from_delta(delta)
-                        })
-                        .collect()
-                })
-                .filter(|path: &Vec<_>| {
-                    // Filter out zigzag moves
-                    if path.len() < 2 {
-                        return true;
-                    }
-                    let windows: Vec<_> = path.chunks(2).collect();
-                    if windows.len() != windows.into_iter().dedup().count() {
-                        return false;
-                    }
-                    let first = path.first().unwrap();
-                    let last = path.last().unwrap();
-                    if first != last {
-                        return true;
-                    }
-
-                    path.iter().all(|cmd| cmd == first)
-                })
-                .collect();
+        for (y, line) in layout.trim().lines().enumerate() {
+            for (x, c) in line.trim().chars().enumerate() {
+                if c == '.' {
+                    continue;
+                }
+
+                if keys.values().any(|k| *k == c) {
+                    return Err(KeypadError::DuplicateKey(c));
+                }
 
-            result.insert((a, b), shortest_paths);
+                keys.insert((x, y), c);
+            }
         }
-    }
 
-    result
-}
+        if !keys.values().any(|k| *k == 'A') {
+            return Err(KeypadError::MissingActivate);
+        }
 
-fn directional_command_to_position(cmd: DirectionalCommand) -> (usize, usize) {
-    match cmd {
-        DirectionalCommand::Up => (1, 0),
-        DirectionalCommand::Down => (1, 1),
-        DirectionalCommand::Left => (0, 1),
-        DirectionalCommand::Right => (2, 1),
-        DirectionalCommand::Activate => (2, 0),
+        Ok(Self { keys })
     }
-}
-
-fn parse_keys(input: &str) -> Vec<Key> {
-    let mut result = vec![];
 
-    for c in input.trim().chars() {
-        result.push(Key::try_from(c).unwrap());
+    fn position(&self, key: char) -> Option<(usize, usize)> {
+        self.keys
+            .iter()
+            .find(|(_, k)| **k == key)
+            .map(|(position, _)| *position)
     }
 
-    result
-}
-
-#[memoize]
-fn compute_shortest_paths_between_commands(
-) -> HashMap<(DirectionalCommand, DirectionalCommand), HashSet<Vec<DirectionalCommand>>> {
-    let all_commands = [
-        DirectionalCommand::Up,
-        DirectionalCommand::Down,
-        DirectionalCommand::Left,
-        DirectionalCommand::Right,
-        DirectionalCommand::Activate,
-    ];
-
-    let mut result = HashMap::new();
-
-    for a in all_commands {
-        for b in all_commands {
-            if a == b {
-                result.insert((a, b), HashSet::new());
-                continue;
-            }
-
-            let start_pos = directional_command_to_position(a);
-            let end_pos = directional_command_to_position(b);
-
-            let Some((shortest_paths, _)) = astar_bag_collect(
-                &start_pos,
-                |&(x, y)| {
-                    [
-                        (x as isize, y as isize - 1),
-                        (x as isize, y as isize + 1),
-                        (x as isize - 1, y as isize),
-                        (x as isize + 1, y as isize),
-                    ]
+    fn shortest_paths(&self) -> ShortestPaths<char> {
+        let mut result = HashMap::new();
+
+        for (&start_pos, &a) in &self.keys {
+            for (&end_pos, &b) in &self.keys {
+                if a == b {
+                    result.insert((a, b), HashSet::new());
+                    continue;
+                }
+
+                let Some((shortest_paths, _)) = astar_bag_collect(
+                    &start_pos,
+                    |&(x, y)| {
+                        [
+                            (x as isize, y as isize - 1),
+                            (x as isize, y as isize + 1),
+                            (x as isize - 1, y as isize),
+                            (x as isize + 1, y as isize),
+                        ]
+                        .into_iter()
+                        .filter(|&(x, y)| x >= 0 && y >= 0)
+                        .map(|(x, y)| (x as usize, y as usize))
+                        .filter(|position| self.keys.contains_key(position))
+                        .map(|position| (position, 1))
+                        .collect::<Vec<_>>()
+                    },
+                    |&(x, y)| x.abs_diff(end_pos.0) + y.abs_diff(end_pos.1),
+                    |position| position == &end_pos,
+                ) else {
+                    panic!("Could not compute shortest paths");
+                };
+
+                let shortest_paths = shortest_paths
                     .into_iter()
-                    .filter_map(|(x, y)| -> Option<(usize, usize)> {
-                        let is_within_bounds =
-                            x >= 0 && y >= 0 && (x as usize) < 3 && (y as usize) < 4;
+                    .map(|positions| {
+                        positions
+                            .iter()
+                            .skip(1)
+                            .enumerate()
+                            .filter_map(|(i, pos)| {
+                                let last = positions[i];
+                                let delta = (
+                                    pos.0 as isize - last.0 as isize,
+                                    pos.1 as isize - last.1 as isize,
+                                );
+                                get_directional_command_from_delta(delta)
+                            })
+                            .collect()
+                    })
+                    .collect();
 
-                        if !is_within_bounds {
-                            return None;
-                        }
+                result.insert((a, b), shortest_paths);
+            }
+        }
 
-                        let x = x as usize;
-                        let y = y as usize;
+        result
+    }
+}
 
-                        if (x, y) == (0, 0) {
-                            // This position is forbidden
-                            return None;
-                        }
+#[cfg(test)]
+fn is_without_zigzag(path: &[DirectionalCommand]) -> bool {
+    if path.len() < 2 {
+        return true;
+    }
+    let windows: Vec<_> = path.chunks(2).collect();
+    if windows.len() != windows.into_iter().dedup().count() {
+        return false;
+    }
+    let first = path.first().unwrap();
+    let last = path.last().unwrap();
+    if first != last {
+        return true;
+    }
 
-                        Some((x, y))
-                    })
-                    .map(|(x, y)| ((x, y), 1))
-                    .collect::<Vec<_>>()
-                },
-                |&(x, y)| {
-                    let (ex, ey) = end_pos;
-                    (((ex as isize - x as isize).pow(2) + (ey as isize - y as isize).pow(2)) as f64)
-                        .sqrt() as usize
-                },
-                |position| position == &end_pos,
-            ) else {
-                panic!("Could not compute shortest paths");
-            };
+    path.iter().all(|cmd| cmd == first)
+}
 
-            let shortest_paths = shortest_paths
+#[cfg(test)]
+#[memoize]
+fn compute_shortest_paths_between_keys() -> ShortestPaths<Key> {
+    Keypad::parse(NUMERIC_KEYPAD)
+        .unwrap()
+        .shortest_paths()
+        .into_iter()
+        .map(|((a, b), paths)| {
+            let a = Key::try_from(a).unwrap();
+            let b = Key::try_from(b).unwrap();
+            // Filter out zigzag moves
+            let paths = paths
                 .into_iter()
-                .map(|positions| {
-                    positions
-                        .iter()
-                        .skip(1)
-                        .enumerate()
-                        .filter_map(|(i, pos)| {
-                            let last = positions[i];
-                            let delta = (
-                                pos.0 as isize - last.0 as isize,
-                                pos.1 as isize - last.1 as isize,
-                            );
-                            get_directional_command_from_delta(delta)
-                        })
-                        .collect()
-                })
+                .filter(|path| is_without_zigzag(path))
                 .collect();
+            ((a, b), paths)
+        })
+        .collect()
+}
 
-            result.insert((a, b), shortest_paths);
-        }
+fn parse_keys(input: &str) -> Vec<Key> {
+    let mut result = vec![];
+
+    for c in input.trim().chars() {
+        result.push(Key::try_from(c).unwrap());
     }
 
     result
 }
 
+#[cfg(test)]
+#[memoize]
+fn compute_shortest_paths_between_commands() -> ShortestPaths<DirectionalCommand> {
+    Keypad::parse(DIRECTIONAL_KEYPAD)
+        .unwrap()
+        .shortest_paths()
+        .into_iter()
+        .map(|((a, b), paths)| {
+            let a = DirectionalCommand::try_from(a).unwrap();
+            let b = DirectionalCommand::try_from(b).unwrap();
+            ((a, b), paths)
+        })
+        .collect()
+}
+
+#[cfg(test)]
 fn encode(code: &[Key]) -> HashSet<Vec<DirectionalCommand>> {
     let shortest_paths_between_keys = compute_shortest_paths_between_keys();
 
@@ -400,6 +360,7 @@ fn encode(code: &[Key]) -> HashSet<Vec<DirectionalCommand>> {
     encoded
 }
 
+#[cfg(test)]
 fn reencode(commands: &[DirectionalCommand]) -> HashSet<Vec<DirectionalCommand>> {
     let shortest_paths_between_commands = compute_shortest_paths_between_commands();
 
@@ -460,7 +421,10 @@ fn reencode(commands: &[DirectionalCommand]) -> HashSet<Vec<DirectionalCommand>>
     encoded
 }
 
-fn find_shortest_encoding(code: &[Key], reencode_n: usize) -> Vec<DirectionalCommand> {
+#[cfg(test)]
+fn enumerate_shortest_encoding(code: &[Key], reencode_n: usize) -> Vec<DirectionalCommand> {
+    use rayon::iter::IntoParallelIterator;
+
     println!(
         "Finding shortest encoding for {}",
         code.iter().map(|k| char::from(*k)).join(",")
@@ -485,6 +449,229 @@ fn find_shortest_encoding(code: &[Key], reencode_n: usize) -> Vec<DirectionalCom
         .unwrap()
 }
 
+// A chain of keypads, starting with the one at the door. Every keypad but the first
+// is operated by a robot that is in turn controlled by the next keypad in the chain,
+// while the last one is operated directly.
+struct KeypadChain {
+    keypads: Vec<Keypad>,
+    shortest_paths: Vec<ShortestPaths<char>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReplayError {
+    Gap {
+        level: usize,
+        position: (isize, isize),
+    },
+    InvalidCommand {
+        level: usize,
+        key: char,
+    },
+}
+
+fn with_activate(path: &[DirectionalCommand]) -> Vec<char> {
+    path.iter()
+        .map(|cmd| char::from(*cmd))
+        .chain(std::iter::once('A'))
+        .collect()
+}
+
+impl KeypadChain {
+    fn new(keypads: &[Keypad]) -> Self {
+        Self {
+            keypads: keypads.to_vec(),
+            // All shortest paths are kept, on arbitrary layouts the only one can be a zigzag
+            shortest_paths: keypads.iter().map(Keypad::shortest_paths).collect(),
+        }
+    }
+
+    fn standard(reencode_n: usize) -> Self {
+        let mut keypads = vec![Keypad::parse(NUMERIC_KEYPAD).unwrap()];
+        keypads.extend(std::iter::repeat_n(
+            Keypad::parse(DIRECTIONAL_KEYPAD).unwrap(),
+            reencode_n + 1,
+        ));
+        Self::new(&keypads)
+    }
+
+    fn encoding_length(&self, code: &[char]) -> usize {
+        let mut cache = HashMap::new();
+        self.sequence_cost(code, 0, &mut cache)
+    }
+
+    fn sequence_cost(
+        &self,
+        keys: &[char],
+        level: usize,
+        cache: &mut HashMap<(usize, char, char), usize>,
+    ) -> usize {
+        std::iter::once('A')
+            .chain(keys.iter().copied())
+            .tuple_windows()
+            .map(|(a, b)| self.press_cost(a, b, level, cache))
+            .sum()
+    }
+
+    // Number of presses needed on the last keypad to move the arm on the keypad at the
+    // given level from one key to another and press it
+    fn press_cost(
+        &self,
+        from: char,
+        to: char,
+        level: usize,
+        cache: &mut HashMap<(usize, char, char), usize>,
+    ) -> usize {
+        if level == self.shortest_paths.len() - 1 {
+            return 1;
+        }
+
+        if let Some(cost) = cache.get(&(level, from, to)) {
+            return *cost;
+        }
+
+        let Some(paths) = self.shortest_paths[level].get(&(from, to)) else {
+            panic!("Cannot move from {from} to {to} on keypad {level}");
+        };
+
+        let cost = if paths.is_empty() {
+            self.sequence_cost(&['A'], level + 1, cache)
+        } else {
+            paths
+                .iter()
+                .map(|path| self.sequence_cost(&with_activate(path), level + 1, cache))
+                .min()
+                .unwrap()
+        };
+
+        cache.insert((level, from, to), cost);
+
+        cost
+    }
+
+    // Returns the cheapest sequence of keys to press on the keypad above the given level
+    // to make its robot type the given keys
+    fn expand(
+        &self,
+        keys: &[char],
+        level: usize,
+        cache: &mut HashMap<(usize, char, char), usize>,
+    ) -> Vec<char> {
+        let mut result = vec![];
+
+        for (from, to) in std::iter::once('A')
+            .chain(keys.iter().copied())
+            .tuple_windows()
+        {
+            let Some(paths) = self.shortest_paths[level].get(&(from, to)) else {
+                panic!("Cannot move from {from} to {to} on keypad {level}");
+            };
+
+            if paths.is_empty() {
+                result.push('A');
+                continue;
+            }
+
+            let best = paths
+                .iter()
+                .map(|path| with_activate(path))
+                .min_by_key(|path| self.sequence_cost(path, level + 1, cache))
+                .unwrap();
+
+            result.extend(best);
+        }
+
+        result
+    }
+
+    // Returns the keys pressed on every keypad, starting with the code itself and ending
+    // with the sequence pressed on the last keypad
+    fn encoding_layers(&self, code: &[char]) -> Vec<Vec<char>> {
+        let mut cache = HashMap::new();
+        let mut layers = vec![code.to_vec()];
+
+        for level in 0..self.keypads.len() - 1 {
+            let next = self.expand(layers.last().unwrap(), level, &mut cache);
+            layers.push(next);
+        }
+
+        layers
+    }
+
+    // Replays keys pressed on the last keypad through the chain and returns the keys
+    // that end up being pressed on the first one
+    fn replay(&self, sequence: &[char]) -> Result<Vec<char>, ReplayError> {
+        let mut current = sequence.to_vec();
+
+        for level in (0..self.keypads.len() - 1).rev() {
+            let keypad = &self.keypads[level];
+
+            let (x, y) = keypad.position('A').unwrap();
+            let mut position = (x as isize, y as isize);
+
+            let mut pressed = vec![];
+
+            for key in current {
+                let Ok(cmd) = DirectionalCommand::try_from(key) else {
+                    return Err(ReplayError::InvalidCommand { level, key });
+                };
+
+                position = match cmd {
+                    DirectionalCommand::Up => (position.0, position.1 - 1),
+                    DirectionalCommand::Down => (position.0, position.1 + 1),
+                    DirectionalCommand::Left => (position.0 - 1, position.1),
+                    DirectionalCommand::Right => (position.0 + 1, position.1),
+                    DirectionalCommand::Activate => position,
+                };
+
+                let key = if position.0 >= 0 && position.1 >= 0 {
+                    keypad
+                        .keys
+                        .get(&(position.0 as usize, position.1 as usize))
+                        .copied()
+                } else {
+                    None
+                };
+
+                let Some(key) = key else {
+                    return Err(ReplayError::Gap { level, position });
+                };
+
+                if cmd == DirectionalCommand::Activate {
+                    pressed.push(key);
+                }
+            }
+
+            current = pressed;
+        }
+
+        Ok(current)
+    }
+}
+
+// Returns the sequence typed on each directional keypad, starting with the one controlling
+// the robot at the door and ending with the one pressed directly
+fn find_shortest_encoding(code: &[Key], reencode_n: usize) -> Vec<Vec<DirectionalCommand>> {
+    let code: Vec<char> = code.iter().map(|k| char::from(*k)).collect();
+
+    KeypadChain::standard(reencode_n)
+        .encoding_layers(&code)
+        .into_iter()
+        .skip(1)
+        .map(|layer| {
+            layer
+                .into_iter()
+                .map(|key| DirectionalCommand::try_from(key).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+fn shortest_encoding_length(code: &[Key], reencode_n: usize) -> usize {
+    let code: Vec<char> = code.iter().map(|k| char::from(*k)).collect();
+
+    KeypadChain::standard(reencode_n).encoding_length(&code)
+}
+
 fn get_numeric_part(code: &[Key]) -> usize {
     code.iter()
         .filter_map(|key| {
@@ -500,8 +687,8 @@ fn get_numeric_part(code: &[Key]) -> usize {
         .unwrap()
 }
 
-fn calculate_checksum(encoded: &[DirectionalCommand], code: &[Key]) -> usize {
-    let a = encoded.len();
+fn calculate_checksum(encoded_length: usize, code: &[Key]) -> usize {
+    let a = encoded_length;
     let b = get_numeric_part(code);
     a * b
 }
@@ -510,8 +697,8 @@ fn calculate_checksums(codes: &[Vec<Key>], reencode_n: usize) -> usize {
     codes
         .par_iter()
         .map(|code| {
-            let encoded = find_shortest_encoding(code, reencode_n);
-            calculate_checksum(&encoded, code)
+            let encoded_length = shortest_encoding_length(code, reencode_n);
+            calculate_checksum(encoded_length, code)
         })
         .sum()
 }
@@ -525,9 +712,38 @@ fn main() {
         .map(|line| parse_keys(line.trim()))
         .collect();
 
+    let chain = KeypadChain::standard(2);
+
+    for code in &codes {
+        let encoding = find_shortest_encoding(code, 2);
+        let top_level: Vec<char> = encoding
+            .last()
+            .unwrap()
+            .iter()
+            .map(|cmd| char::from(*cmd))
+            .collect();
+
+        let replayed = chain.replay(&top_level).expect("Failed to replay encoding");
+
+        assert_eq!(
+            replayed,
+            code.iter().map(|k| char::from(*k)).collect::<Vec<_>>()
+        );
+
+        println!(
+            "{}: {}",
+            code.iter().map(|k| char::from(*k)).join(""),
+            top_level.iter().join("")
+        );
+    }
+
     let result = calculate_checksums(&codes, 2);
 
     println!("Result (Part 1): {result}");
+
+    let result = calculate_checksums(&codes, 25);
+
+    println!("Result (Part 2): {result}");
 }
 
 #[cfg(test)]
@@ -724,13 +940,31 @@ mod tests {
     }
 
     #[test]
-    fn test_find_shortest_encoding() {
-        assert_eq!(find_shortest_encoding(&parse_keys("029A"), 0).len(), 12);
-        assert_eq!(find_shortest_encoding(&parse_keys("029A"), 2).len(), 68);
-        assert_eq!(find_shortest_encoding(&parse_keys("980A"), 2).len(), 60);
-        assert_eq!(find_shortest_encoding(&parse_keys("179A"), 2).len(), 68);
-        assert_eq!(find_shortest_encoding(&parse_keys("456A"), 2).len(), 64);
-        assert_eq!(find_shortest_encoding(&parse_keys("379A"), 2).len(), 64);
+    fn test_enumerate_shortest_encoding() {
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("029A"), 0).len(),
+            12
+        );
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("029A"), 2).len(),
+            68
+        );
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("980A"), 2).len(),
+            60
+        );
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("179A"), 2).len(),
+            68
+        );
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("456A"), 2).len(),
+            64
+        );
+        assert_eq!(
+            enumerate_shortest_encoding(&parse_keys("379A"), 2).len(),
+            64
+        );
     }
 
     #[test]
@@ -747,6 +981,159 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shortest_encoding_length() {
+        for code in ["029A", "980A", "179A", "456A", "379A"] {
+            let code = parse_keys(code);
+
+            for reencode_n in 0..=2 {
+                assert_eq!(
+                    shortest_encoding_length(&code, reencode_n),
+                    enumerate_shortest_encoding(&code, reencode_n).len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_checksums_deep() {
+        assert_eq!(
+            calculate_checksums(
+                &["029A", "980A", "179A", "456A", "379A"]
+                    .into_iter()
+                    .map(parse_keys)
+                    .collect::<Vec<_>>(),
+                25
+            ),
+            154115708116294
+        );
+    }
+
+    #[test]
+    fn test_keypad_avoids_gap() {
+        let shortest_paths = Keypad::parse(DIRECTIONAL_KEYPAD).unwrap().shortest_paths();
+
+        assert_eq!(
+            shortest_paths.get(&('<', '^')).unwrap(),
+            &[vec![DirectionalCommand::Right, DirectionalCommand::Up]]
+                .into_iter()
+                .collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn test_keypad_chain_custom_layout() {
+        let wide = Keypad::parse("12345A").unwrap();
+        let directional = Keypad::parse(DIRECTIONAL_KEYPAD).unwrap();
+
+        let chain = KeypadChain::new(&[wide.clone(), directional.clone()]);
+        assert_eq!(chain.encoding_length(&['5', 'A']), 4);
+
+        let chain = KeypadChain::new(&[wide, directional.clone(), directional]);
+        assert_eq!(chain.encoding_length(&['5', 'A']), 12);
+    }
+
+    #[test]
+    fn test_keypad_chain_phone_layout() {
+        let phone = Keypad::parse(
+            "
+123
+456
+789
+.0A
+",
+        )
+        .unwrap();
+        let directional = Keypad::parse(DIRECTIONAL_KEYPAD).unwrap();
+
+        let chain = KeypadChain::new(&[phone, directional]);
+        assert_eq!(chain.encoding_length(&['0', '2', '9', 'A']), 12);
+    }
+
+    #[test]
+    fn test_keypad_chain_zigzag_layout() {
+        let zigzag = Keypad::parse(
+            "
+A1.
+.2b
+",
+        )
+        .unwrap();
+        let directional = Keypad::parse(DIRECTIONAL_KEYPAD).unwrap();
+
+        let chain = KeypadChain::new(&[zigzag, directional]);
+        assert_eq!(chain.encoding_length(&['b']), 4);
+        assert_eq!(chain.replay(&['>', 'v', '>', 'A']), Ok(vec!['b']));
+    }
+
+    #[test]
+    fn test_keypad_parse_errors() {
+        assert_eq!(Keypad::parse("123"), Err(KeypadError::MissingActivate));
+        assert_eq!(Keypad::parse("1A1"), Err(KeypadError::DuplicateKey('1')));
+    }
+
+    #[test]
+    fn test_find_shortest_encoding() {
+        let encoding = find_shortest_encoding(&parse_keys("029A"), 2);
+
+        assert_eq!(
+            encoding.iter().map(|layer| layer.len()).collect::<Vec<_>>(),
+            vec![12, 28, 68]
+        );
+
+        let keypads = KeypadChain::standard(2).keypads;
+
+        for (i, layer) in encoding.iter().enumerate() {
+            let keys: Vec<char> = layer.iter().map(|cmd| char::from(*cmd)).collect();
+
+            assert_eq!(
+                KeypadChain::new(&keypads[..i + 2]).replay(&keys),
+                Ok("029A".chars().collect())
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let chain = KeypadChain::standard(2);
+
+        for code in ["029A", "980A", "179A", "456A", "379A"] {
+            let layers = chain.encoding_layers(&code.chars().collect::<Vec<_>>());
+
+            assert_eq!(
+                chain.replay(layers.last().unwrap()),
+                Ok(code.chars().collect())
+            );
+        }
+
+        let example = "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A";
+        assert_eq!(
+            chain.replay(&example.chars().collect::<Vec<_>>()),
+            Ok("029A".chars().collect())
+        );
+    }
+
+    #[test]
+    fn test_replay_gap() {
+        let chain = KeypadChain::new(&[
+            Keypad::parse(NUMERIC_KEYPAD).unwrap(),
+            Keypad::parse(DIRECTIONAL_KEYPAD).unwrap(),
+        ]);
+
+        assert_eq!(chain.replay(&['<', 'A']), Ok(vec!['0']));
+        assert_eq!(
+            chain.replay(&['<', '<', 'A']),
+            Err(ReplayError::Gap {
+                level: 0,
+                position: (0, 3)
+            })
+        );
+        assert_eq!(
+            chain.replay(&['1']),
+            Err(ReplayError::InvalidCommand { level: 0, key: '1' })
+        );
+    }
+
     #[test]
     fn test_get_numeric_part() {
         assert_eq!(get_numeric_part(&parse_keys("029A")), 29);
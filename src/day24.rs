@@ -1,11 +1,11 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fs,
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs,
 };
 
 use itertools::Itertools;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Op {
     And,
     Or,
@@ -25,6 +25,277 @@ impl TryFrom<&str> for Op {
     }
 }
 
+impl Op {
+    fn apply(&self, a: bool, b: bool) -> bool {
+        match self {
+            Op::And => a && b,
+            Op::Or => a || b,
+            Op::Xor => a != b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CircuitError {
+    MultipleDrivers(String),
+    Cycle(Vec<String>),
+    MissingInput(String),
+}
+
+#[derive(Debug, Clone)]
+struct Gate {
+    op: Op,
+    inputs: [usize; 2],
+    output: usize,
+}
+
+// A system with interned wire ids, where every wire is driven by at most one gate
+// and the gates are kept in an order in which they can be evaluated
+struct Circuit {
+    wires: Vec<String>,
+    gates: Vec<Gate>,
+    drivers: Vec<Option<usize>>,
+    order: Vec<usize>,
+}
+
+impl Circuit {
+    fn topological_order(&self) -> Result<Vec<usize>, CircuitError> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        fn visit(
+            circuit: &Circuit,
+            wire: usize,
+            states: &mut [State],
+            path: &mut Vec<usize>,
+            order: &mut Vec<usize>,
+        ) -> Result<(), CircuitError> {
+            match states[wire] {
+                State::Done => return Ok(()),
+                State::InProgress => {
+                    let loop_start = path.iter().position(|w| *w == wire).unwrap();
+                    let wires = path[loop_start..]
+                        .iter()
+                        .map(|w| circuit.wires[*w].clone())
+                        .collect();
+                    return Err(CircuitError::Cycle(wires));
+                }
+                State::Unvisited => {}
+            }
+
+            if let Some(gate) = circuit.drivers[wire] {
+                states[wire] = State::InProgress;
+                path.push(wire);
+
+                for input in circuit.gates[gate].inputs {
+                    visit(circuit, input, states, path, order)?;
+                }
+
+                path.pop();
+                order.push(gate);
+            }
+
+            states[wire] = State::Done;
+
+            Ok(())
+        }
+
+        let mut states = vec![State::Unvisited; self.wires.len()];
+        let mut path = vec![];
+        let mut order = vec![];
+
+        for wire in 0..self.wires.len() {
+            visit(self, wire, &mut states, &mut path, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    fn evaluate(&self, values: &mut [bool]) {
+        for gate in &self.order {
+            let gate = &self.gates[*gate];
+            values[gate.output] = gate
+                .op
+                .apply(values[gate.inputs[0]], values[gate.inputs[1]]);
+        }
+    }
+
+    // Evaluates only the gates the given outputs depend on. A wire with an initial value
+    // keeps it even if a gate drives it, so the gate and its inputs are never needed.
+    fn evaluate_from(
+        &self,
+        node_values: &HashMap<String, bool>,
+        outputs: &[usize],
+    ) -> Result<Vec<Option<bool>>, CircuitError> {
+        let mut values: Vec<Option<bool>> = self
+            .wires
+            .iter()
+            .map(|wire| node_values.get(wire).copied())
+            .collect();
+
+        let mut needed = vec![false; self.gates.len()];
+        let mut visited = vec![false; self.wires.len()];
+        let mut stack = outputs.to_vec();
+
+        while let Some(wire) = stack.pop() {
+            if visited[wire] || values[wire].is_some() {
+                continue;
+            }
+
+            visited[wire] = true;
+
+            let Some(gate) = self.drivers[wire] else {
+                return Err(CircuitError::MissingInput(self.wires[wire].clone()));
+            };
+
+            needed[gate] = true;
+            stack.extend(self.gates[gate].inputs);
+        }
+
+        for gate in self.order.iter().filter(|gate| needed[**gate]) {
+            let gate = &self.gates[*gate];
+            let [a, b] = gate.inputs.map(|input| values[input].unwrap());
+            values[gate.output] = Some(gate.op.apply(a, b));
+        }
+
+        Ok(values)
+    }
+
+    fn bus(&self, prefix: char) -> Vec<usize> {
+        self.wires
+            .iter()
+            .enumerate()
+            .filter(|(_, wire)| wire.starts_with(prefix))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn read_bus(&self, values: &[bool], prefix: char) -> u64 {
+        self.bus(prefix)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, id)| values[*id])
+            .fold(0, |acc, (bit, _)| acc | 1 << bit)
+    }
+
+    fn write_bus(&self, values: &mut [bool], prefix: char, value: u64) {
+        for (bit, id) in self.bus(prefix).into_iter().enumerate() {
+            values[id] = bit < 64 && value >> bit & 1 == 1;
+        }
+    }
+
+    fn simulate(&self, x: u64, y: u64) -> u64 {
+        let mut values = vec![false; self.wires.len()];
+
+        self.write_bus(&mut values, 'x', x);
+        self.write_bus(&mut values, 'y', y);
+
+        self.evaluate(&mut values);
+
+        self.read_bus(&values, 'z')
+    }
+
+    // Runs the circuit on single-bit, carry-chain and pseudo-random inputs and returns
+    // the mismatch with the lowest failing output bit
+    fn verify(&self, operation: fn(u64, u64) -> u64, random_samples: usize) -> Option<Mismatch> {
+        let input_bits = self.bus('x').len().max(self.bus('y').len()).min(63);
+        let output_bits = self.bus('z').len().min(64);
+
+        let input_mask = (1u64 << input_bits) - 1;
+        let output_mask = if output_bits == 64 {
+            u64::MAX
+        } else {
+            (1u64 << output_bits) - 1
+        };
+
+        let mut patterns = vec![];
+
+        for bit in 0..input_bits {
+            patterns.push((1 << bit, 0));
+            patterns.push((0, 1 << bit));
+            patterns.push((1 << bit, 1 << bit));
+        }
+
+        for bit in 0..input_bits {
+            let ones = (1u64 << (bit + 1)) - 1;
+            patterns.push((ones, 1));
+            patterns.push((1, ones));
+            patterns.push((ones, ones));
+        }
+
+        let mut state = 0x2545f4914f6cdd1d_u64;
+        for _ in 0..random_samples {
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state & input_mask
+            };
+            patterns.push((next(), next()));
+        }
+
+        patterns
+            .into_iter()
+            .filter_map(|(x, y)| {
+                let expected = operation(x, y) & output_mask;
+                let actual = self.simulate(x, y);
+
+                if expected == actual {
+                    return None;
+                }
+
+                Some(Mismatch {
+                    x,
+                    y,
+                    expected,
+                    actual,
+                    bit: (expected ^ actual).trailing_zeros() as usize,
+                })
+            })
+            .min_by_key(|m| m.bit)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mismatch {
+    x: u64,
+    y: u64,
+    expected: u64,
+    actual: u64,
+    bit: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AdderRule {
+    OutputNotXor,
+    InnerXorNotOutput,
+    InputXorNotFeedingXor,
+    AndNotFeedingOr,
+}
+
+impl AdderRule {
+    fn explanation(&self) -> &'static str {
+        match self {
+            AdderRule::OutputNotXor => {
+                "z-wires must be driven by an XOR gate (only the final carry-out by an OR gate)"
+            }
+            AdderRule::InnerXorNotOutput => {
+                "an XOR gate on intermediate wires computes a sum bit and must drive a z-wire"
+            }
+            AdderRule::InputXorNotFeedingXor => {
+                "an XOR gate on x/y inputs computes a half sum and must feed another XOR gate"
+            }
+            AdderRule::AndNotFeedingOr => {
+                "an AND gate (except for bit 0) computes a partial carry and must feed an OR gate"
+            }
+        }
+    }
+}
+
 struct System {
     node_values: HashMap<String, bool>,
     nodes: HashSet<String>,
@@ -76,57 +347,222 @@ impl System {
         }
     }
 
-    fn solve_node(&mut self, node: &str) {
-        if self.node_values.contains_key(node) {
-            return;
-        }
+    fn compile(&self) -> Result<Circuit, CircuitError> {
+        let wires = self.nodes.iter().cloned().sorted().collect_vec();
 
-        let edges = self.edges.clone();
+        let wire_ids: HashMap<String, usize> = wires
+            .iter()
+            .enumerate()
+            .map(|(id, wire)| (wire.clone(), id))
+            .collect();
 
-        let Some((i1, op, i2, _)) = edges.iter().find(|(_, _, _, x)| x == node) else {
-            panic!("Node {node} does not have an input edge");
-        };
+        let mut gates = vec![];
+        let mut drivers = vec![None; wires.len()];
 
-        if !&self.node_values.contains_key(i1) {
-            self.solve_node(i1);
-        }
+        for (i1, op, i2, out) in self.edges.iter().sorted() {
+            let output = wire_ids[out];
 
-        if !&self.node_values.contains_key(i2) {
-            self.solve_node(i2);
-        }
+            if drivers[output].is_some() {
+                return Err(CircuitError::MultipleDrivers(out.clone()));
+            }
 
-        let i1v = self.node_values[i1];
-        let i2v = self.node_values[i2];
+            drivers[output] = Some(gates.len());
+            gates.push(Gate {
+                op: *op,
+                inputs: [wire_ids[i1], wire_ids[i2]],
+                output,
+            });
+        }
 
-        let v = match op {
-            Op::And => i1v && i2v,
-            Op::Or => i1v || i2v,
-            Op::Xor => i1v != i2v,
+        let mut circuit = Circuit {
+            wires,
+            gates,
+            drivers,
+            order: vec![],
         };
 
-        self.node_values.insert(node.to_string(), v);
+        circuit.order = circuit.topological_order()?;
+
+        Ok(circuit)
     }
 
     fn solve(&mut self) -> isize {
-        let mut nodes = self.nodes.clone().into_iter().collect_vec();
+        let circuit = self.compile().expect("Failed to compile circuit");
+
+        let values = circuit
+            .evaluate_from(&self.node_values, &circuit.bus('z'))
+            .expect("Failed to evaluate circuit");
 
-        nodes.sort();
+        for (wire, value) in circuit.wires.iter().zip(&values) {
+            if let Some(value) = value {
+                self.node_values.insert(wire.clone(), *value);
+            }
+        }
 
-        let res: Vec<bool> = nodes
+        let values = values
             .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect_vec();
+
+        circuit.read_bus(&values, 'z') as isize
+    }
+
+    fn verify(&self, operation: fn(u64, u64) -> u64, random_samples: usize) -> Option<Mismatch> {
+        self.compile()
+            .expect("Failed to compile circuit")
+            .verify(operation, random_samples)
+    }
+
+    // Checks every gate against the structure of a ripple-carry adder and returns
+    // the outputs that break it, sorted by wire name
+    fn find_swapped_wires(&self) -> Vec<(String, AdderRule)> {
+        let is_input = |n: &str| n.starts_with('x') || n.starts_with('y');
+        let is_first_input = |n: &str| n == "x00" || n == "y00";
+
+        let last_output = self
+            .nodes
+            .iter()
             .filter(|n| n.starts_with('z'))
-            .map(|n| {
-                self.solve_node(&n);
-                self.node_values[&n]
-            })
-            .rev()
-            .collect();
+            .max()
+            .cloned()
+            .unwrap_or_default();
+
+        let feeds = |node: &str, op: Op| {
+            self.edges
+                .iter()
+                .any(|(i1, o, i2, _)| *o == op && (i1 == node || i2 == node))
+        };
+
+        let mut swapped = BTreeMap::new();
+
+        for (i1, op, i2, out) in &self.edges {
+            let expected_output_op = if *out == last_output { Op::Or } else { Op::Xor };
+
+            let rule = if out.starts_with('z') && *op != expected_output_op {
+                Some(AdderRule::OutputNotXor)
+            } else if *op == Op::Xor && !is_input(i1) && !is_input(i2) && !out.starts_with('z') {
+                Some(AdderRule::InnerXorNotOutput)
+            } else if *op == Op::Xor
+                && is_input(i1)
+                && is_input(i2)
+                && !is_first_input(i1)
+                && !feeds(out, Op::Xor)
+            {
+                Some(AdderRule::InputXorNotFeedingXor)
+            } else if *op == Op::And && !is_first_input(i1) && !feeds(out, Op::Or) {
+                Some(AdderRule::AndNotFeedingOr)
+            } else {
+                None
+            };
+
+            if let Some(rule) = rule {
+                swapped.insert(out.clone(), rule);
+            }
+        }
 
-        isize::from_str_radix(
-            &res.into_iter().map(|v| if v { '1' } else { '0' }).join(""),
-            2,
-        )
-        .unwrap()
+        swapped.into_iter().collect()
+    }
+
+    fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph circuit {".to_string(),
+            "    rankdir=LR;".to_string(),
+        ];
+
+        for prefix in ['x', 'y', 'z'] {
+            let bus = self
+                .nodes
+                .iter()
+                .filter(|n| n.starts_with(prefix))
+                .sorted()
+                .join("; ");
+
+            if bus.is_empty() {
+                continue;
+            }
+
+            let rank = if prefix == 'z' { "sink" } else { "source" };
+
+            lines.push(format!("    {{ rank={rank}; {bus}; }}"));
+        }
+
+        for node in self
+            .nodes
+            .iter()
+            .filter(|n| n.starts_with('x') || n.starts_with('y'))
+            .sorted()
+        {
+            lines.push(format!("    {node} [shape=circle];"));
+        }
+
+        for (i1, op, i2, out) in self.edges.iter().sorted() {
+            let (label, shape) = match op {
+                Op::And => ("AND", "box"),
+                Op::Or => ("OR", "ellipse"),
+                Op::Xor => ("XOR", "diamond"),
+            };
+
+            lines.push(format!(
+                "    {out} [label=\"{out}\\n{label}\", shape={shape}];"
+            ));
+            lines.push(format!("    {i1} -> {out};"));
+            lines.push(format!("    {i2} -> {out};"));
+        }
+
+        lines.push("}".to_string());
+
+        lines.join("\n")
+    }
+
+    fn to_verilog(&self, module_name: &str) -> String {
+        let driven: HashSet<&String> = self.edges.iter().map(|(_, _, _, out)| out).collect();
+
+        let inputs = self
+            .nodes
+            .iter()
+            .filter(|n| !driven.contains(n))
+            .sorted()
+            .collect_vec();
+
+        let outputs = self
+            .nodes
+            .iter()
+            .filter(|n| n.starts_with('z') && driven.contains(n))
+            .sorted()
+            .collect_vec();
+
+        let wires = self
+            .nodes
+            .iter()
+            .filter(|n| !n.starts_with('z') && driven.contains(n))
+            .sorted()
+            .collect_vec();
+
+        let ports = inputs
+            .iter()
+            .map(|n| format!("input {n}"))
+            .chain(outputs.iter().map(|n| format!("output {n}")))
+            .join(", ");
+
+        let mut lines = vec![format!("module {module_name}({ports});")];
+
+        if !wires.is_empty() {
+            lines.push(format!("    wire {};", wires.iter().join(", ")));
+        }
+
+        for (i, (i1, op, i2, out)) in self.edges.iter().sorted().enumerate() {
+            let primitive = match op {
+                Op::And => "and",
+                Op::Or => "or",
+                Op::Xor => "xor",
+            };
+
+            lines.push(format!("    {primitive} g{i}({out}, {i1}, {i2});"));
+        }
+
+        lines.push("endmodule".to_string());
+
+        lines.join("\n")
     }
 }
 
@@ -138,6 +574,33 @@ fn main() {
     let result = s.solve();
 
     println!("Result (Part 1): {result}");
+
+    let swapped = s.find_swapped_wires();
+
+    let result = swapped.iter().map(|(wire, _)| wire).join(",");
+
+    println!("Result (Part 2): {result}");
+
+    for (wire, rule) in &swapped {
+        println!("  {wire}: {}", rule.explanation());
+    }
+
+    if let Some(path) = env::args().skip_while(|arg| arg != "--dot").nth(1) {
+        fs::write(&path, s.to_dot()).expect("Failed to write file");
+        println!("Wrote Graphviz circuit to {path}");
+    }
+
+    if let Some(path) = env::args().skip_while(|arg| arg != "--verilog").nth(1) {
+        fs::write(&path, s.to_verilog("day24")).expect("Failed to write file");
+        println!("Wrote Verilog module to {path}");
+    }
+
+    if let Some(mismatch) = s.verify(|x, y| x.wrapping_add(y), 1000) {
+        println!(
+            "  Lowest failing bit: z{:02} ({} + {} = {}, got {})",
+            mismatch.bit, mismatch.x, mismatch.y, mismatch.expected, mismatch.actual
+        );
+    }
 }
 
 #[cfg(test)]
@@ -239,4 +702,318 @@ tnw OR pbm -> gnj
 
         assert_eq!(s.solve(), 2024);
     }
+
+    #[test]
+    fn test_compile_reevaluate() {
+        let s = System::parse(&ripple_carry_adder(5, &[]));
+
+        let circuit = s.compile().unwrap();
+
+        let mut values = circuit
+            .evaluate_from(&s.node_values, &circuit.bus('z'))
+            .unwrap()
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect_vec();
+        assert_eq!(circuit.read_bus(&values, 'z'), 0b11111);
+
+        let y00 = circuit.wires.iter().position(|w| w == "y00").unwrap();
+        values[y00] = true;
+        circuit.evaluate(&mut values);
+        assert_eq!(circuit.read_bus(&values, 'z'), 0b100000);
+    }
+
+    #[test]
+    fn test_evaluate_output_cone() {
+        let input = r#"
+x00: 1
+y00: 0
+
+x00 XOR y00 -> z00
+x00 AND abc -> def
+        "#;
+
+        let s = System::parse(input);
+        let circuit = s.compile().unwrap();
+
+        // abc is neither driven nor given, but z00 does not depend on it
+        let values = circuit.evaluate_from(&s.node_values, &circuit.bus('z'));
+        let def = circuit.wires.iter().position(|w| w == "def").unwrap();
+        assert_eq!(values.as_ref().map(|values| values[def]), Ok(None));
+        assert_eq!(System::parse(input).solve(), 1);
+
+        assert_eq!(
+            circuit.evaluate_from(&s.node_values, &[def]),
+            Err(CircuitError::MissingInput("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_initial_value_precedence() {
+        let input = r#"
+x00: 1
+y00: 1
+z00: 0
+
+x00 AND y00 -> z00
+x00 OR y00 -> z01
+        "#;
+
+        // Initial values win over the gates driving the same wire
+        assert_eq!(System::parse(input).solve(), 0b10);
+    }
+
+    #[test]
+    fn test_compile_cycle() {
+        let input = r#"
+x00: 1
+y00: 0
+
+x00 AND abc -> z00
+y00 OR def -> abc
+abc XOR x00 -> def
+        "#;
+
+        let s = System::parse(input);
+
+        assert_eq!(
+            s.compile().err(),
+            Some(CircuitError::Cycle(vec![
+                "abc".to_string(),
+                "def".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_compile_multiple_drivers() {
+        let input = r#"
+x00: 1
+y00: 0
+
+x00 AND y00 -> z00
+x00 OR y00 -> z00
+        "#;
+
+        let s = System::parse(input);
+
+        assert_eq!(
+            s.compile().err(),
+            Some(CircuitError::MultipleDrivers("z00".to_string()))
+        );
+    }
+
+    fn ripple_carry_adder(bits: usize, swaps: &[(&str, &str)]) -> String {
+        let mut gates = vec![
+            (
+                "x00".to_string(),
+                "XOR",
+                "y00".to_string(),
+                "z00".to_string(),
+            ),
+            (
+                "x00".to_string(),
+                "AND",
+                "y00".to_string(),
+                "c00".to_string(),
+            ),
+        ];
+
+        for i in 1..bits {
+            let carry_in = format!("c{:02}", i - 1);
+            let carry_out = if i == bits - 1 {
+                format!("z{bits:02}")
+            } else {
+                format!("c{i:02}")
+            };
+
+            gates.push((
+                format!("x{i:02}"),
+                "XOR",
+                format!("y{i:02}"),
+                format!("a{i:02}"),
+            ));
+            gates.push((
+                format!("x{i:02}"),
+                "AND",
+                format!("y{i:02}"),
+                format!("b{i:02}"),
+            ));
+            gates.push((
+                format!("a{i:02}"),
+                "XOR",
+                carry_in.clone(),
+                format!("z{i:02}"),
+            ));
+            gates.push((format!("a{i:02}"), "AND", carry_in, format!("d{i:02}")));
+            gates.push((format!("b{i:02}"), "OR", format!("d{i:02}"), carry_out));
+        }
+
+        for (_, _, _, out) in gates.iter_mut() {
+            for (a, b) in swaps {
+                if out == a {
+                    *out = b.to_string();
+                } else if out == b {
+                    *out = a.to_string();
+                }
+            }
+        }
+
+        let initial_values = (0..bits)
+            .flat_map(|i| [format!("x{i:02}: 1"), format!("y{i:02}: 0")])
+            .join("\n");
+
+        let connections = gates
+            .into_iter()
+            .map(|(i1, op, i2, out)| format!("{i1} {op} {i2} -> {out}"))
+            .join("\n");
+
+        format!("{initial_values}\n\n{connections}")
+    }
+
+    #[test]
+    fn test_find_swapped_wires_correct_adder() {
+        let s = System::parse(&ripple_carry_adder(5, &[]));
+
+        assert_eq!(s.find_swapped_wires(), vec![]);
+    }
+
+    #[test]
+    fn test_find_swapped_wires() {
+        let s = System::parse(&ripple_carry_adder(5, &[("z02", "c02"), ("a03", "b03")]));
+
+        assert_eq!(
+            s.find_swapped_wires(),
+            vec![
+                ("a03".to_string(), AdderRule::AndNotFeedingOr),
+                ("b03".to_string(), AdderRule::InputXorNotFeedingXor),
+                ("c02".to_string(), AdderRule::InnerXorNotOutput),
+                ("z02".to_string(), AdderRule::OutputNotXor),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simulate() {
+        let s = System::parse(&ripple_carry_adder(8, &[]));
+
+        let circuit = s.compile().unwrap();
+
+        assert_eq!(circuit.simulate(0, 0), 0);
+        assert_eq!(circuit.simulate(200, 100), 300);
+        assert_eq!(circuit.simulate(255, 255), 510);
+    }
+
+    #[test]
+    fn test_verify_correct_adder() {
+        let s = System::parse(&ripple_carry_adder(8, &[]));
+
+        assert_eq!(s.verify(|x, y| x + y, 100), None);
+    }
+
+    #[test]
+    fn test_verify_swapped_adder() {
+        let s = System::parse(&ripple_carry_adder(8, &[("z05", "c05"), ("a03", "b03")]));
+
+        assert_eq!(s.verify(|x, y| x + y, 100).map(|m| m.bit), Some(3));
+    }
+
+    #[test]
+    fn test_verify_and() {
+        let input = r#"
+x00: 0
+x01: 1
+x02: 0
+y00: 0
+y01: 0
+y02: 1
+
+x00 AND y00 -> z00
+x01 AND y01 -> z02
+x02 AND y02 -> z01
+        "#;
+
+        let s = System::parse(input);
+
+        assert_eq!(
+            s.verify(|x, y| x & y, 0),
+            Some(Mismatch {
+                x: 2,
+                y: 2,
+                expected: 2,
+                actual: 4,
+                bit: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let input = r#"
+x00: 1
+y00: 0
+
+x00 AND y00 -> abc
+abc XOR x00 -> z00
+        "#;
+
+        let s = System::parse(input);
+
+        assert_eq!(
+            s.to_dot(),
+            r#"digraph circuit {
+    rankdir=LR;
+    { rank=source; x00; }
+    { rank=source; y00; }
+    { rank=sink; z00; }
+    x00 [shape=circle];
+    y00 [shape=circle];
+    z00 [label="z00\nXOR", shape=diamond];
+    abc -> z00;
+    x00 -> z00;
+    abc [label="abc\nAND", shape=box];
+    x00 -> abc;
+    y00 -> abc;
+}"#
+        );
+    }
+
+    #[test]
+    fn test_to_dot_skips_empty_bus() {
+        let input = r#"
+x00: 1
+x01: 0
+
+x00 OR x01 -> z00
+        "#;
+
+        let s = System::parse(input);
+
+        assert!(!s.to_dot().contains("{ rank=source; ; }"));
+        assert_eq!(s.to_dot().matches("rank=").count(), 2);
+    }
+
+    #[test]
+    fn test_to_verilog() {
+        let input = r#"
+x00: 1
+y00: 0
+
+x00 AND y00 -> abc
+abc XOR x00 -> z00
+y00 OR abc -> z01
+        "#;
+
+        let s = System::parse(input);
+
+        assert_eq!(
+            s.to_verilog("example"),
+            r#"module example(input x00, input y00, output z00, output z01);
+    wire abc;
+    xor g0(z00, abc, x00);
+    and g1(abc, x00, y00);
+    or g2(z01, y00, abc);
+endmodule"#
+        );
+    }
 }